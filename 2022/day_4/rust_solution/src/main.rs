@@ -1,5 +1,21 @@
 use std::fs;
 
+/// Characters separating the bounds of a range (`2-4`) and the ranges
+/// assigned to a group of elves (`2-4,6-8`).
+struct Delimiters {
+    range: char,
+    group: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Delimiters {
+        Delimiters {
+            range: '-',
+            group: ',',
+        }
+    }
+}
+
 fn pair_contained(left_pair: (u32, u32), right_pair: (u32, u32)) -> bool {
     (left_pair.0 >= right_pair.0 && left_pair.1 <= right_pair.1)
         || (right_pair.0 >= left_pair.0 && right_pair.1 <= left_pair.1)
@@ -12,38 +28,97 @@ fn pair_partial_overlap(left_pair: (u32, u32), right_pair: (u32, u32)) -> bool {
         || (left_pair.0 <= right_pair.1 && left_pair.0 >= right_pair.0)
 }
 
-fn decompose_line(line: &str) -> ((u32, u32), (u32, u32)) {
-    let line: Vec<u32> = line
-        .split(&['-', ','])
-        .map(|str| str.parse().unwrap())
-        .collect();
-    ((line[0], line[1]), (line[2], line[3]))
+// Sorting by start, and by end descending for equal starts, puts every
+// range after any range that could contain it. A range is then contained
+// as soon as its end does not go past the furthest end seen so far.
+fn any_contained(ranges: &[(u32, u32)]) -> bool {
+    if let [left, right] = ranges {
+        return pair_contained(*left, *right);
+    }
+    let mut sorted = ranges.to_vec();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    let mut max_end = None;
+    for range in sorted {
+        match max_end {
+            Some(end) if range.1 <= end => return true,
+            _ => max_end = Some(range.1),
+        }
+    }
+    false
 }
 
-fn count_contained_pairs(content: &str) -> u32 {
-    content
-        .lines()
-        .map(|line| decompose_line(line))
-        .map(|pairs| pair_contained(pairs.0, pairs.1) as u32)
-        .sum()
+// Once sorted by start, two ranges overlap only if some range starts
+// before the furthest end seen so far.
+fn any_overlap(ranges: &[(u32, u32)]) -> bool {
+    if let [left, right] = ranges {
+        return pair_partial_overlap(*left, *right);
+    }
+    let mut sorted = ranges.to_vec();
+    sorted.sort();
+    let mut max_end = None;
+    for range in sorted {
+        match max_end {
+            Some(end) if range.0 <= end => return true,
+            Some(end) => max_end = Some(range.1.max(end)),
+            None => max_end = Some(range.1),
+        }
+    }
+    false
 }
 
-fn count_partial_pairs(content: &str) -> u32 {
+fn all_overlap(ranges: &[(u32, u32)]) -> bool {
+    let latest_start = ranges.iter().map(|range| range.0).max();
+    let earliest_end = ranges.iter().map(|range| range.1).min();
+    match (latest_start, earliest_end) {
+        (Some(start), Some(end)) => start <= end,
+        _ => false,
+    }
+}
+
+fn decompose_line(line: &str, delimiters: &Delimiters) -> Vec<(u32, u32)> {
+    line.split(delimiters.group)
+        .map(|range| {
+            let (start, end) = range.split_once(delimiters.range).unwrap();
+            (start.trim().parse().unwrap(), end.trim().parse().unwrap())
+        })
+        .collect()
+}
+
+fn count_groups(content: &str, delimiters: &Delimiters, check: fn(&[(u32, u32)]) -> bool) -> u32 {
     content
         .lines()
-        .map(|line| decompose_line(line))
-        .map(|pairs| pair_partial_overlap(pairs.0, pairs.1) as u32)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| decompose_line(line, delimiters))
+        .map(|ranges| check(&ranges) as u32)
         .sum()
 }
 
+fn count_contained_groups(content: &str, delimiters: &Delimiters) -> u32 {
+    count_groups(content, delimiters, any_contained)
+}
+
+fn count_overlapping_groups(content: &str, delimiters: &Delimiters) -> u32 {
+    count_groups(content, delimiters, any_overlap)
+}
+
+fn count_common_groups(content: &str, delimiters: &Delimiters) -> u32 {
+    count_groups(content, delimiters, all_overlap)
+}
+
 fn main() {
     let content = fs::read_to_string("../input").expect("Missing input file.");
-    let contained_pairs = count_contained_pairs(&content);
+    let delimiters = Delimiters::default();
+    let contained_pairs = count_contained_groups(&content, &delimiters);
     println!("The number of contained pairs is {}", contained_pairs);
-    let partial_pairs = count_partial_pairs(&content);
+    let partial_pairs = count_overlapping_groups(&content, &delimiters);
     println!(
         "The number of partially overlapping pairs is {}",
         partial_pairs
+    );
+    let common_groups = count_common_groups(&content, &delimiters);
+    println!(
+        "The number of groups sharing a common section is {}",
+        common_groups
     )
 }
 
@@ -67,6 +142,72 @@ mod tests {
 
     #[test]
     fn test_decompose_line() {
-        assert_eq!(((2, 6), (4, 8)), decompose_line("2-6,4-8"))
+        let delimiters = Delimiters::default();
+        assert_eq!(vec![(2, 6), (4, 8)], decompose_line("2-6,4-8", &delimiters));
+        assert_eq!(
+            vec![(2, 6), (4, 8), (1, 1)],
+            decompose_line("2-6,4-8,1-1", &delimiters)
+        );
+
+        let delimiters = Delimiters {
+            range: ':',
+            group: ';',
+        };
+        assert_eq!(vec![(2, 6), (4, 8)], decompose_line("2:6;4:8", &delimiters))
+    }
+
+    #[test]
+    fn test_groups_match_pairs() {
+        let sample = [
+            (2, 4),
+            (6, 8),
+            (2, 3),
+            (4, 5),
+            (5, 7),
+            (7, 9),
+            (2, 8),
+            (3, 7),
+            (6, 6),
+            (4, 6),
+            (2, 6),
+            (4, 8),
+        ];
+        for left in sample {
+            for right in sample {
+                assert_eq!(
+                    pair_contained(left, right),
+                    any_contained(&[left, right, (100, 100)])
+                );
+                assert_eq!(
+                    pair_partial_overlap(left, right),
+                    any_overlap(&[left, right, (100, 100)])
+                );
+                assert_eq!(
+                    pair_partial_overlap(left, right),
+                    all_overlap(&[left, right])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_checks() {
+        assert!(any_contained(&[(1, 2), (5, 9), (6, 7)]));
+        assert!(!any_contained(&[(1, 3), (2, 5), (4, 9)]));
+
+        assert!(any_overlap(&[(1, 2), (8, 9), (1, 20), (4, 5)]));
+        assert!(!any_overlap(&[(7, 9), (1, 2), (4, 5)]));
+
+        assert!(all_overlap(&[(1, 5), (3, 9), (5, 5)]));
+        assert!(!all_overlap(&[(1, 5), (3, 9), (6, 7)]));
+    }
+
+    #[test]
+    fn test_count_groups() {
+        let content = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8\n";
+        let delimiters = Delimiters::default();
+        assert_eq!(2, count_contained_groups(content, &delimiters));
+        assert_eq!(4, count_overlapping_groups(content, &delimiters));
+        assert_eq!(4, count_common_groups(content, &delimiters));
     }
 }
@@ -1,4 +1,12 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::io::{self, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
 
 fn chunk_string(row: &str) -> Vec<String> {
     row.chars()
@@ -8,10 +16,8 @@ fn chunk_string(row: &str) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
-fn decompose_row(row: &Vec<String>) -> Vec<String> {
-    row.iter()
-        .map(|s| s.replace(" ", "").replace("[", "").replace("]", ""))
-        .collect()
+fn decompose_row(row: &[String]) -> Vec<String> {
+    row.iter().map(|s| s.replace([' ', '[', ']'], "")).collect()
 }
 
 fn parse_move_line(line: &str) -> (u32, usize, usize) {
@@ -22,81 +28,759 @@ fn parse_move_line(line: &str) -> (u32, usize, usize) {
     (n_crates, from, to)
 }
 
-#[derive(Debug)]
+/// A single `move N from A to B` instruction, with `from` and `to` given
+/// as stack labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    n_crates: u32,
+    from: usize,
+    to: usize,
+}
+
+impl Move {
+    pub fn new(n_crates: u32, from: usize, to: usize) -> Move {
+        Move { n_crates, from, to }
+    }
+
+    pub fn from_line(line: &str) -> Move {
+        let (n_crates, from, to) = parse_move_line(line);
+        Move::new(n_crates, from, to)
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.n_crates, self.from, self.to
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MoveError {
+    UnknownStack(usize),
+    NotEnoughCrates {
+        label: usize,
+        requested: u32,
+        available: usize,
+    },
+    NoSuchStep {
+        step: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::UnknownStack(label) => write!(f, "there is no stack labelled {}", label),
+            MoveError::NotEnoughCrates {
+                label,
+                requested,
+                available,
+            } => write!(
+                f,
+                "stack {} holds {} crates but {} were requested",
+                label, available, requested
+            ),
+            MoveError::NoSuchStep { step, available } => write!(
+                f,
+                "cannot replay to step {}, only {} moves are recorded",
+                step, available
+            ),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DiagramError {
+    /// The diagram has no footer row of stack labels.
+    MissingFooter,
+    /// The footer holds something other than numbers.
+    InvalidLabel(String),
+    /// A crate row, counted from 1 at the top, has crates past the last
+    /// labelled stack.
+    TooWide { line: usize },
+}
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagramError::MissingFooter => write!(f, "the diagram has no stack labels"),
+            DiagramError::InvalidLabel(footer) => {
+                write!(f, "the footer {:?} does not label stacks by number", footer)
+            }
+            DiagramError::TooWide { line } => {
+                write!(f, "line {} has crates beyond the last stack", line)
+            }
+        }
+    }
+}
+
+impl Error for DiagramError {}
+
+/// A single crate, identified by the letter painted on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Crate(char);
+
+/// A labelled stack of crates, bottom first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Stack {
+    label: usize,
+    crates: Vec<Crate>,
+}
+
+impl Stack {
+    pub fn new(label: usize) -> Stack {
+        Stack {
+            label,
+            crates: vec![],
+        }
+    }
+
+    pub fn push(&mut self, item: Crate) {
+        self.crates.push(item)
+    }
+
+    pub fn top(&self) -> Option<Crate> {
+        self.crates.last().copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Crane {
-    crane: Vec<Vec<String>>,
+    stacks: Vec<Stack>,
 }
 
 impl Crane {
-    pub fn from_diagram(diagram: &str) -> Crane {
-        let rows: Vec<&str> = diagram.split("\n").collect();
-        let mut rows = rows.iter().rev();
-        // get the first rwo which has the number of crates
-        let columns: Vec<&str> = rows.next().unwrap().split_whitespace().collect();
-        let columns: Vec<u32> = columns.iter().map(|s| s.parse().unwrap()).collect();
-        let size = *columns.last().unwrap() as usize;
-
-        let mut crane: Vec<Vec<String>> = vec![vec![]; size];
+    pub fn from_diagram(diagram: &str) -> Result<Crane, DiagramError> {
+        let rows: Vec<&str> = diagram.lines().collect();
+        let (footer, rows) = rows.split_last().ok_or(DiagramError::MissingFooter)?;
+        // the footer row holds the label of every stack
+        let mut stacks = chunk_string(footer)
+            .iter()
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+            .map(|label| label.parse().map(Stack::new))
+            .collect::<Result<Vec<Stack>, _>>()
+            .map_err(|_| DiagramError::InvalidLabel(footer.trim().to_string()))?;
+
         // iter over rows and insert into columns
-        let rows = rows
-            .map(|row| chunk_string(row))
-            .map(|row| decompose_row(&row));
-        for row in rows {
+        for (line, row) in rows.iter().enumerate().rev() {
+            let row = decompose_row(&chunk_string(row));
             for (idx, item) in row.iter().enumerate() {
-                if !item.is_empty() {
-                    crane[idx].push(item.to_string())
+                if let Some(letter) = item.chars().next() {
+                    let stack = stacks
+                        .get_mut(idx)
+                        .ok_or(DiagramError::TooWide { line: line + 1 })?;
+                    stack.push(Crate(letter))
                 }
             }
         }
 
-        Crane { crane }
+        Ok(Crane { stacks })
     }
 
-    pub fn move_crates(&mut self, n_crates: u32, from: usize, to: usize, as_stack: bool) {
-        if as_stack {
-            for _ in 0..n_crates {
-                let popped = self.crane[from - 1].pop().unwrap();
-                self.crane[to - 1].push(popped);
-            }
+    /// Render the crane in the puzzle's `[X]` diagram format, including the
+    /// numbered footer, so that `from_diagram(&to_diagram())` is lossless.
+    pub fn to_diagram(&self) -> String {
+        let height = self.stacks.iter().map(|stack| stack.crates.len()).max();
+        let mut rows: Vec<String> = (0..height.unwrap_or(0))
+            .rev()
+            .map(|level| {
+                self.stacks
+                    .iter()
+                    .map(|stack| match stack.crates.get(level) {
+                        Some(item) => format!("[{}]", item.0),
+                        None => "   ".to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect();
+        let footer = self
+            .stacks
+            .iter()
+            .map(|stack| format!("{:^3}", stack.label))
+            .collect::<Vec<String>>()
+            .join(" ");
+        rows.push(footer);
+        rows.join("\n")
+    }
+
+    /// Index of the stack carrying `label` in the footer.
+    pub fn position(&self, label: usize) -> Option<usize> {
+        self.stacks.iter().position(|stack| stack.label == label)
+    }
+
+    /// Check `mv` against the current state without changing anything,
+    /// returning the stack indices it moves between.
+    pub fn validate(&self, mv: &Move) -> Result<(usize, usize), MoveError> {
+        let from = self
+            .position(mv.from)
+            .ok_or(MoveError::UnknownStack(mv.from))?;
+        let to = self.position(mv.to).ok_or(MoveError::UnknownStack(mv.to))?;
+        let available = self.stacks[from].crates.len();
+        if (mv.n_crates as usize) > available {
+            return Err(MoveError::NotEnoughCrates {
+                label: mv.from,
+                requested: mv.n_crates,
+                available,
+            });
+        }
+        Ok((from, to))
+    }
+
+    /// Mutable access to two distinct stacks at once.
+    fn pair_mut(&mut self, first: usize, second: usize) -> (&mut Stack, &mut Stack) {
+        if first < second {
+            let (head, tail) = self.stacks.split_at_mut(second);
+            (&mut head[first], &mut tail[0])
         } else {
-            let mut to_move = vec![];
-            for _ in 0..n_crates {
-                let element = self.crane[from - 1].pop().unwrap();
-                to_move.insert(0, element)
-            }
-            self.crane[to - 1].append(&mut to_move);
+            let (head, tail) = self.stacks.split_at_mut(first);
+            (&mut tail[0], &mut head[second])
+        }
+    }
+
+    pub fn move_crates(
+        &mut self,
+        n_crates: u32,
+        from: usize,
+        to: usize,
+        model: &dyn CraneModel,
+    ) -> Result<(), MoveError> {
+        let (from, to) = self.validate(&Move::new(n_crates, from, to))?;
+        // lifting crates off a stack and back onto it changes nothing
+        if from != to {
+            let (from, to) = self.pair_mut(from, to);
+            model.lift(n_crates as usize, from, to);
         }
+        Ok(())
     }
 
-    pub fn get_tops(self) -> String {
-        self.crane
+    /// The top crate of every stack, `None` for empty stacks.
+    pub fn tops(&self) -> Vec<Option<Crate>> {
+        self.stacks.iter().map(|stack| stack.top()).collect()
+    }
+
+    /// The top crates as a string, with a space standing in for empty stacks.
+    pub fn get_tops(&self) -> String {
+        self.tops()
             .iter()
-            .map(|c| c.last().unwrap().to_string())
+            .map(|top| top.map_or(' ', |c| c.0))
             .collect()
     }
 }
 
-fn main() {
-    let contents = fs::read_to_string("../input.txt").expect("Missing input file");
-    let contents: Vec<&str> = contents.split("\n\n").collect();
+/// A crane together with the log of moves applied to it, supporting
+/// undo, redo and replaying up to any step.
+struct History<'a> {
+    current: Crane,
+    model: &'a dyn CraneModel,
+    // every move with the crates it took off its source, bottom first
+    moves: Vec<(Move, Vec<Crate>)>,
+    applied: usize,
+}
 
-    let mut crane = Crane::from_diagram(contents[0]);
+impl<'a> History<'a> {
+    pub fn new(crane: Crane, model: &'a dyn CraneModel) -> History<'a> {
+        History {
+            current: crane,
+            model,
+            moves: vec![],
+            applied: 0,
+        }
+    }
 
-    for line in contents[1].lines() {
-        let (n_crates, from, to) = parse_move_line(line);
-        crane.move_crates(n_crates, from, to, true);
+    pub fn crane(&self) -> &Crane {
+        &self.current
     }
 
-    println!("Part One Answer: {}\n", crane.get_tops());
+    /// The number of moves currently applied.
+    pub fn step(&self) -> usize {
+        self.applied
+    }
 
-    let mut crane = Crane::from_diagram(contents[0]);
+    /// Apply a new move, discarding any moves which could have been redone.
+    pub fn apply(&mut self, mv: Move) -> Result<(), MoveError> {
+        let (from, _) = self.current.validate(&mv)?;
+        let crates = &self.current.stacks[from].crates;
+        let lifted = crates[crates.len() - mv.n_crates as usize..].to_vec();
+        self.current
+            .move_crates(mv.n_crates, mv.from, mv.to, self.model)?;
+        self.moves.truncate(self.applied);
+        self.moves.push((mv, lifted));
+        self.applied += 1;
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Option<Move> {
+        if self.applied == 0 {
+            return None;
+        }
+        self.applied -= 1;
+        let (mv, lifted) = &self.moves[self.applied];
+        // the crates moved by `mv` sit on top of its target, whatever their order
+        let from = self.current.position(mv.from).unwrap();
+        let to = self.current.position(mv.to).unwrap();
+        if from != to {
+            let to = &mut self.current.stacks[to].crates;
+            to.truncate(to.len() - lifted.len());
+            self.current.stacks[from].crates.extend(lifted);
+        }
+        Some(*mv)
+    }
+
+    pub fn redo(&mut self) -> Option<Move> {
+        let (mv, _) = *self.moves.get(self.applied)?;
+        self.current
+            .move_crates(mv.n_crates, mv.from, mv.to, self.model)
+            .expect("redo of a validated move");
+        self.applied += 1;
+        Some(mv)
+    }
 
-    for line in contents[1].lines() {
+    /// Undo or redo recorded moves until exactly `step` of them are applied.
+    pub fn replay_to(&mut self, step: usize) -> Result<(), MoveError> {
+        if step > self.moves.len() {
+            return Err(MoveError::NoSuchStep {
+                step,
+                available: self.moves.len(),
+            });
+        }
+        while self.applied > step {
+            self.undo();
+        }
+        while self.applied < step {
+            self.redo();
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Crane {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_diagram())
+    }
+}
+
+/// How a crane carries crates from one stack to another.
+trait CraneModel {
+    fn name(&self) -> String;
+
+    /// Move the top `n_crates` of `from` onto `to`. The caller has already
+    /// checked that `from` holds enough crates.
+    fn lift(&self, n_crates: usize, from: &mut Stack, to: &mut Stack);
+}
+
+/// Lifts one crate at a time, reversing the order of the moved crates.
+struct CrateMover9000;
+
+impl CraneModel for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn lift(&self, n_crates: usize, from: &mut Stack, to: &mut Stack) {
+        let start = from.crates.len() - n_crates;
+        to.crates.extend(from.crates.drain(start..).rev());
+    }
+}
+
+/// Lifts all the crates at once, keeping their order.
+struct CrateMover9001;
+
+impl CraneModel for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn lift(&self, n_crates: usize, from: &mut Stack, to: &mut Stack) {
+        let start = from.crates.len() - n_crates;
+        to.crates.extend(from.crates.drain(start..));
+    }
+}
+
+/// Keeps the order within a lift like the 9001, but carries at most
+/// `capacity` crates at a time, splitting larger moves into several lifts.
+struct LimitedCrateMover {
+    capacity: usize,
+}
+
+impl LimitedCrateMover {
+    pub fn new(capacity: usize) -> LimitedCrateMover {
+        assert!(capacity > 0, "a crane must carry at least one crate");
+        LimitedCrateMover { capacity }
+    }
+}
+
+impl CraneModel for LimitedCrateMover {
+    fn name(&self) -> String {
+        format!("CrateMover limited to {}", self.capacity)
+    }
+
+    fn lift(&self, n_crates: usize, from: &mut Stack, to: &mut Stack) {
+        let mut remaining = n_crates;
+        while remaining > 0 {
+            let lifted = remaining.min(self.capacity);
+            let start = from.crates.len() - lifted;
+            to.crates.extend(from.crates.drain(start..));
+            remaining -= lifted;
+        }
+    }
+}
+
+/// Look up a crane model by the name given on the command line: `9000`,
+/// `9001` or `limited:<capacity>`.
+fn parse_model(name: &str) -> Option<Box<dyn CraneModel>> {
+    match name {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        _ => {
+            let capacity: usize = name.strip_prefix("limited:")?.parse().ok()?;
+            (capacity > 0)
+                .then(|| Box::new(LimitedCrateMover::new(capacity)) as Box<dyn CraneModel>)
+        }
+    }
+}
+
+/// What a trace emits after every move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceMode {
+    /// The full rendered diagram.
+    Diagram,
+    /// Only the stacks which changed, as `label: before -> after`.
+    Diff,
+}
+
+struct Tracer<W: Write> {
+    mode: TraceMode,
+    delay: Option<Duration>,
+    out: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(mode: TraceMode, delay: Option<Duration>, out: W) -> Tracer<W> {
+        Tracer { mode, delay, out }
+    }
+
+    pub fn start(&mut self, title: &str, crane: &Crane) -> io::Result<()> {
+        writeln!(self.out, "== {} ==", title)?;
+        writeln!(self.out, "{}\n", crane)
+    }
+
+    pub fn frame(
+        &mut self,
+        step: usize,
+        line: &str,
+        before: &Crane,
+        after: &Crane,
+    ) -> io::Result<()> {
+        writeln!(self.out, "[{}] {}", step, line)?;
+        match self.mode {
+            TraceMode::Diagram => writeln!(self.out, "{}\n", after)?,
+            TraceMode::Diff => writeln!(self.out, "{}\n", diff_stacks(before, after))?,
+        }
+        self.out.flush()?;
+        if let Some(delay) = self.delay {
+            thread::sleep(delay);
+        }
+        Ok(())
+    }
+}
+
+fn stack_letters(stack: &Stack) -> String {
+    stack.crates.iter().map(|c| c.0).collect()
+}
+
+/// One line per stack that differs between the two states.
+fn diff_stacks(before: &Crane, after: &Crane) -> String {
+    before
+        .stacks
+        .iter()
+        .zip(after.stacks.iter())
+        .filter(|(old, new)| old != new)
+        .map(|(old, new)| {
+            format!(
+                "{}: {} -> {}",
+                new.label,
+                stack_letters(old),
+                stack_letters(new)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn run_moves<W: Write>(
+    crane: &mut Crane,
+    moves: &str,
+    model: &dyn CraneModel,
+    mut tracer: Option<&mut Tracer<W>>,
+) -> Result<(), Box<dyn Error>> {
+    for (step, line) in moves.lines().enumerate() {
         let (n_crates, from, to) = parse_move_line(line);
-        crane.move_crates(n_crates, from, to, false);
+        let before = tracer.as_ref().map(|_| crane.clone());
+        crane
+            .move_crates(n_crates, from, to, model)
+            .map_err(|err| format!("step {} ({}): {}", step + 1, line, err))?;
+        if let (Some(tracer), Some(before)) = (tracer.as_mut(), before) {
+            tracer.frame(step + 1, line, &before, crane)?;
+        }
     }
+    Ok(())
+}
+
+/// Record every move in a `History` and rewind it to `step`.
+fn replay_moves<'a>(
+    crane: Crane,
+    moves: &str,
+    model: &'a dyn CraneModel,
+    step: usize,
+) -> Result<History<'a>, MoveError> {
+    let mut history = History::new(crane, model);
+    for line in moves.lines() {
+        history.apply(Move::from_line(line))?;
+    }
+    history.replay_to(step)?;
+    Ok(history)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SearchError {
+    /// The two diagrams do not have the same stack labels.
+    MismatchedStacks,
+    /// The two diagrams do not hold the same crates.
+    MismatchedCrates,
+    Unreachable,
+    LimitReached(usize),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::MismatchedStacks => write!(f, "the diagrams have different stacks"),
+            SearchError::MismatchedCrates => write!(f, "the diagrams hold different crates"),
+            SearchError::Unreachable => write!(f, "the target cannot be reached"),
+            SearchError::LimitReached(limit) => {
+                write!(f, "gave up after exploring {} states", limit)
+            }
+        }
+    }
+}
+
+impl Error for SearchError {}
 
-    println!("Part Two Answer: {}", crane.get_tops());
+fn sorted_crates(crane: &Crane) -> Vec<char> {
+    let mut crates: Vec<char> = crane
+        .stacks
+        .iter()
+        .flat_map(|stack| stack.crates.iter().map(|c| c.0))
+        .collect();
+    crates.sort_unstable();
+    crates
+}
+
+/// Breadth-first search for a shortest sequence of moves turning `start`
+/// into `target` under `model`, exploring at most `max_states` diagrams.
+fn find_moves(
+    start: &Crane,
+    target: &Crane,
+    model: &dyn CraneModel,
+    max_states: usize,
+) -> Result<Vec<Move>, SearchError> {
+    let labels = |crane: &Crane| crane.stacks.iter().map(|s| s.label).collect::<Vec<_>>();
+    if labels(start) != labels(target) {
+        return Err(SearchError::MismatchedStacks);
+    }
+    if sorted_crates(start) != sorted_crates(target) {
+        return Err(SearchError::MismatchedCrates);
+    }
+
+    // every state reached, with the state and move it was reached from
+    let mut parents: HashMap<Crane, Option<(Crane, Move)>> = HashMap::new();
+    let mut queue = VecDeque::from([start.clone()]);
+    parents.insert(start.clone(), None);
+    if start == target {
+        return Ok(vec![]);
+    }
+
+    while let Some(crane) = queue.pop_front() {
+        for from in &crane.stacks {
+            for to in crane.stacks.iter().filter(|to| to.label != from.label) {
+                for n_crates in 1..=from.crates.len() as u32 {
+                    let mv = Move::new(n_crates, from.label, to.label);
+                    let mut next = crane.clone();
+                    next.move_crates(mv.n_crates, mv.from, mv.to, model)
+                        .expect("search only generates valid moves");
+                    if parents.contains_key(&next) {
+                        continue;
+                    }
+                    if &next == target {
+                        let mut moves = vec![mv];
+                        let mut current = &crane;
+                        while let Some((parent, mv)) = &parents[current] {
+                            moves.push(*mv);
+                            current = parent;
+                        }
+                        moves.reverse();
+                        return Ok(moves);
+                    }
+                    if parents.len() >= max_states {
+                        return Err(SearchError::LimitReached(max_states));
+                    }
+                    parents.insert(next.clone(), Some((crane.clone(), mv)));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    Err(SearchError::Unreachable)
+}
+
+struct Config {
+    pub filename: String,
+    pub trace: Option<TraceMode>,
+    pub delay: Option<Duration>,
+    pub trace_file: Option<String>,
+    pub step: Option<usize>,
+    pub model: Option<String>,
+    pub target: Option<String>,
+    pub max_states: usize,
+}
+
+impl Config {
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
+        // iter past name
+        args.next();
+
+        let mut config = Config {
+            filename: "../input.txt".to_string(),
+            trace: None,
+            delay: None,
+            trace_file: None,
+            step: None,
+            model: None,
+            target: None,
+            max_states: 1_000_000,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trace" => config.trace = Some(TraceMode::Diagram),
+                "--trace-diff" => config.trace = Some(TraceMode::Diff),
+                "--delay" => {
+                    let millis = args.next().ok_or("Missing value for --delay")?;
+                    let millis = millis.parse().map_err(|_| "Invalid value for --delay")?;
+                    config.delay = Some(Duration::from_millis(millis));
+                }
+                "--trace-file" => {
+                    config.trace_file = Some(args.next().ok_or("Missing value for --trace-file")?);
+                }
+                "--step" => {
+                    let step = args.next().ok_or("Missing value for --step")?;
+                    config.step = Some(step.parse().map_err(|_| "Invalid value for --step")?);
+                }
+                "--model" => {
+                    let model = args.next().ok_or("Missing value for --model")?;
+                    parse_model(&model).ok_or("Unknown crane model")?;
+                    config.model = Some(model);
+                }
+                "--solve" => {
+                    config.target = Some(args.next().ok_or("Missing value for --solve")?);
+                }
+                "--max-states" => {
+                    let limit = args.next().ok_or("Missing value for --max-states")?;
+                    config.max_states = limit
+                        .parse()
+                        .map_err(|_| "Invalid value for --max-states")?;
+                }
+                _ if arg.starts_with("--") => return Err("Unknown option"),
+                _ => config.filename = arg,
+            }
+        }
+        if config.trace.is_none() && (config.delay.is_some() || config.trace_file.is_some()) {
+            config.trace = Some(TraceMode::Diagram);
+        }
+        Ok(config)
+    }
+}
+
+fn main() {
+    let config = Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        process::exit(1);
+    });
+    let contents = fs::read_to_string(&config.filename).expect("Missing input file");
+    let contents: Vec<&str> = contents.split("\n\n").collect();
+
+    let parse_diagram = |diagram: &str| {
+        Crane::from_diagram(diagram).unwrap_or_else(|err| {
+            eprintln!("Invalid crate diagram: {}", err);
+            process::exit(1);
+        })
+    };
+    let start = parse_diagram(contents[0]);
+
+    if let Some(target) = &config.target {
+        let target = fs::read_to_string(target).expect("Missing target diagram");
+        let target = parse_diagram(target.split("\n\n").next().unwrap());
+        let model = parse_model(config.model.as_deref().unwrap_or("9000")).unwrap();
+        match find_moves(&start, &target, model.as_ref(), config.max_states) {
+            Ok(moves) => moves.iter().for_each(|mv| println!("{}", mv)),
+            Err(err) => {
+                eprintln!("Unable to find moves: {}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut tracer = config.trace.map(|mode| {
+        let out: Box<dyn Write> = match &config.trace_file {
+            Some(path) => Box::new(fs::File::create(path).expect("Unable to create trace file")),
+            None => Box::new(io::stdout()),
+        };
+        Tracer::new(mode, config.delay, out)
+    });
+
+    let runs: Vec<(String, Box<dyn CraneModel>)> = match &config.model {
+        Some(name) => {
+            let model = parse_model(name).unwrap();
+            vec![(model.name(), model)]
+        }
+        None => vec![
+            ("Part One".to_string(), Box::new(CrateMover9000)),
+            ("Part Two".to_string(), Box::new(CrateMover9001)),
+        ],
+    };
+
+    for (title, model) in runs {
+        let mut crane = start.clone();
+        if let Some(step) = config.step {
+            match replay_moves(crane, contents[1], model.as_ref(), step) {
+                Ok(history) => println!(
+                    "{} after step {}:\n{}\n",
+                    title,
+                    history.step(),
+                    history.crane()
+                ),
+                Err(err) => eprintln!("{}: {}", title, err),
+            }
+            continue;
+        }
+        if let Some(tracer) = tracer.as_mut() {
+            tracer.start(&title, &crane).expect("Unable to write trace");
+        }
+        if let Err(err) = run_moves(&mut crane, contents[1], model.as_ref(), tracer.as_mut()) {
+            eprintln!("{}: {}", title, err);
+            process::exit(1);
+        }
+        println!("{} Answer: {}\n", title, crane.get_tops());
+    }
 }
 
 #[cfg(test)]
@@ -130,77 +814,341 @@ mod tests {
         assert_eq!("", result[2])
     }
 
+    fn stacks(crane: &Crane) -> Vec<String> {
+        crane
+            .stacks
+            .iter()
+            .map(|stack| stack.crates.iter().map(|c| c.0).collect())
+            .collect()
+    }
+
     #[test]
     fn test_crane() {
         let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3  ";
-        let mut crane = Crane::from_diagram(diagram);
-        assert_eq!(
-            vec![
-                vec!["Z".to_owned(), "N".to_owned()],
-                vec!["M".to_owned(), "C".to_owned(), "D".to_owned()],
-                vec!["P".to_owned()]
-            ],
-            crane.crane
-        );
-
-        crane.move_crates(1, 2, 1, true);
-        assert_eq!(
-            vec![
-                vec!["Z".to_owned(), "N".to_owned(), "D".to_owned()],
-                vec!["M".to_owned(), "C".to_owned()],
-                vec!["P".to_owned()]
-            ],
-            crane.crane
-        );
-
-        crane.move_crates(3, 1, 3, true);
-        assert_eq!(
-            vec![
-                vec![],
-                vec!["M".to_owned(), "C".to_owned()],
-                vec![
-                    "P".to_owned(),
-                    "D".to_owned(),
-                    "N".to_owned(),
-                    "Z".to_owned()
-                ]
-            ],
-            crane.crane
-        );
-
-        crane.move_crates(2, 2, 1, true);
-        assert_eq!(
-            vec![
-                vec!["C".to_owned(), "M".to_owned()],
-                vec![],
-                vec![
-                    "P".to_owned(),
-                    "D".to_owned(),
-                    "N".to_owned(),
-                    "Z".to_owned()
-                ]
-            ],
-            crane.crane
-        );
-
-        crane.move_crates(1, 1, 2, true);
-        assert_eq!(
-            vec![
-                vec!["C".to_owned()],
-                vec!["M".to_owned()],
-                vec![
-                    "P".to_owned(),
-                    "D".to_owned(),
-                    "N".to_owned(),
-                    "Z".to_owned()
-                ]
-            ],
-            crane.crane
-        );
+        let mut crane = Crane::from_diagram(diagram).unwrap();
+        assert_eq!(vec!["ZN", "MCD", "P"], stacks(&crane));
+
+        crane.move_crates(1, 2, 1, &CrateMover9000).unwrap();
+        assert_eq!(vec!["ZND", "MC", "P"], stacks(&crane));
+
+        crane.move_crates(3, 1, 3, &CrateMover9000).unwrap();
+        assert_eq!(vec!["", "MC", "PDNZ"], stacks(&crane));
+        assert_eq!(vec![None, Some(Crate('C')), Some(Crate('Z'))], crane.tops());
+        assert_eq!(" CZ", crane.get_tops());
+
+        crane.move_crates(2, 2, 1, &CrateMover9000).unwrap();
+        assert_eq!(vec!["CM", "", "PDNZ"], stacks(&crane));
+
+        crane.move_crates(1, 1, 2, &CrateMover9000).unwrap();
+        assert_eq!(vec!["C", "M", "PDNZ"], stacks(&crane));
 
         assert_eq!("CMZ".to_owned(), crane.get_tops())
     }
 
+    #[test]
+    fn test_crane_labels() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 4   7   9  ";
+        let mut crane = Crane::from_diagram(diagram).unwrap();
+        assert_eq!(
+            vec![4, 7, 9],
+            crane.stacks.iter().map(|s| s.label).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(1), crane.position(7));
+        assert_eq!(None, crane.position(2));
+
+        crane.move_crates(3, 7, 4, &CrateMover9001).unwrap();
+        assert_eq!(vec!["ZNMCD", "", "P"], stacks(&crane));
+        assert_eq!("D P", crane.get_tops());
+    }
+
+    #[test]
+    fn test_to_diagram() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let mut crane = Crane::from_diagram(diagram).unwrap();
+        assert_eq!(diagram, crane.to_diagram());
+
+        crane.move_crates(1, 2, 1, &CrateMover9000).unwrap();
+        let expected = "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        assert_eq!(expected, crane.to_diagram());
+        assert_eq!(crane, Crane::from_diagram(&crane.to_diagram()).unwrap());
+        assert_eq!(crane.to_diagram(), format!("{}", crane));
+
+        crane.move_crates(1, 3, 2, &CrateMover9000).unwrap();
+        crane.move_crates(2, 2, 1, &CrateMover9000).unwrap();
+        assert_eq!(crane, Crane::from_diagram(&crane.to_diagram()).unwrap());
+    }
+
+    #[test]
+    fn test_diagram_errors() {
+        assert_eq!(Err(DiagramError::MissingFooter), Crane::from_diagram(""));
+        assert_eq!(
+            Err(DiagramError::TooWide { line: 2 }),
+            Crane::from_diagram("    [D]    \n[N] [C]     [Q]\n[Z] [M] [P]\n 1   2   3 ")
+        );
+        assert_eq!(
+            Err(DiagramError::InvalidLabel("1   2   x".to_string())),
+            Crane::from_diagram("[N] [C] [D]\n 1   2   x ")
+        );
+        assert_eq!(
+            "line 2 has crates beyond the last stack",
+            DiagramError::TooWide { line: 2 }.to_string()
+        );
+
+        // trailing blanks past the last stack are fine
+        let crane = Crane::from_diagram("[N]        \n 1  ").unwrap();
+        assert_eq!("N", crane.get_tops());
+    }
+
+    #[test]
+    fn test_trace() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let moves = "move 1 from 2 to 1\nmove 3 from 1 to 3\n";
+
+        let mut crane = Crane::from_diagram(diagram).unwrap();
+        let mut tracer = Tracer::new(TraceMode::Diff, None, vec![]);
+        run_moves(&mut crane, moves, &CrateMover9000, Some(&mut tracer)).unwrap();
+        assert_eq!(
+            "[1] move 1 from 2 to 1\n1: ZN -> ZND\n2: MCD -> MC\n\n\
+             [2] move 3 from 1 to 3\n1: ZND -> \n3: P -> PDNZ\n\n",
+            String::from_utf8(tracer.out).unwrap()
+        );
+
+        let mut crane = Crane::from_diagram(diagram).unwrap();
+        let mut tracer = Tracer::new(TraceMode::Diagram, None, vec![]);
+        run_moves(
+            &mut crane,
+            "move 1 from 2 to 1",
+            &CrateMover9000,
+            Some(&mut tracer),
+        )
+        .unwrap();
+        assert_eq!(
+            "[1] move 1 from 2 to 1\n[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\n",
+            String::from_utf8(tracer.out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let args = ["bin", "--trace-diff", "--delay", "50", "sample.txt"];
+        let config = Config::new(args.iter().map(|s| s.to_string())).unwrap();
+        assert_eq!(Some(TraceMode::Diff), config.trace);
+        assert_eq!(Some(Duration::from_millis(50)), config.delay);
+        assert_eq!("sample.txt", config.filename);
+
+        let args = ["bin", "--trace-file", "frames.txt"];
+        let config = Config::new(args.iter().map(|s| s.to_string())).unwrap();
+        assert_eq!(Some(TraceMode::Diagram), config.trace);
+        assert_eq!(Some("frames.txt".to_string()), config.trace_file);
+
+        let args = ["bin", "--delay"];
+        assert!(Config::new(args.iter().map(|s| s.to_string())).is_err());
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let mut crane = Crane::from_diagram(diagram).unwrap();
+
+        assert_eq!(
+            Err(MoveError::UnknownStack(0)),
+            crane.move_crates(1, 0, 1, &CrateMover9000)
+        );
+        assert_eq!(
+            Err(MoveError::UnknownStack(4)),
+            crane.move_crates(1, 1, 4, &CrateMover9000)
+        );
+        assert_eq!(
+            Err(MoveError::NotEnoughCrates {
+                label: 1,
+                requested: 3,
+                available: 2
+            }),
+            crane.move_crates(3, 1, 2, &CrateMover9001)
+        );
+        // a rejected move leaves the crane untouched
+        assert_eq!(Crane::from_diagram(diagram).unwrap(), crane);
+
+        let mut tracer: Option<&mut Tracer<Vec<u8>>> = None;
+        let result = run_moves(
+            &mut crane,
+            "move 1 from 2 to 1\nmove 9 from 3 to 1",
+            &CrateMover9000,
+            tracer.take(),
+        );
+        assert_eq!(
+            "step 2 (move 9 from 3 to 1): stack 3 holds 1 crates but 9 were requested",
+            result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let moves =
+            "move 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2";
+        let models: [&dyn CraneModel; 3] =
+            [&CrateMover9000, &CrateMover9001, &LimitedCrateMover::new(2)];
+        for model in models {
+            let initial = Crane::from_diagram(diagram).unwrap();
+            let mut history = History::new(initial.clone(), model);
+            let mut states = vec![initial.clone()];
+            for line in moves.lines() {
+                history.apply(Move::from_line(line)).unwrap();
+                states.push(history.crane().clone());
+            }
+            assert_eq!(4, history.step());
+
+            for step in (0..4).rev() {
+                assert_eq!(
+                    Some(Move::from_line(moves.lines().nth(step).unwrap())),
+                    history.undo()
+                );
+                assert_eq!(&states[step], history.crane());
+            }
+            assert_eq!(None, history.undo());
+
+            assert_eq!(Some(Move::new(1, 2, 1)), history.redo());
+            assert_eq!(&states[1], history.crane());
+
+            history.replay_to(3).unwrap();
+            assert_eq!(&states[3], history.crane());
+            assert_eq!(
+                Err(MoveError::NoSuchStep {
+                    step: 5,
+                    available: 4
+                }),
+                history.replay_to(5)
+            );
+
+            // a new move drops the redo tail
+            history.replay_to(1).unwrap();
+            assert_eq!(
+                Err(MoveError::NotEnoughCrates {
+                    label: 2,
+                    requested: 4,
+                    available: 2
+                }),
+                history.apply(Move::new(4, 2, 3))
+            );
+            history.apply(Move::new(1, 3, 2)).unwrap();
+            assert_eq!(None, history.redo());
+            assert_eq!(2, history.step());
+        }
+
+        assert_eq!(
+            "CMZ",
+            replay_moves(
+                Crane::from_diagram(diagram).unwrap(),
+                moves,
+                &CrateMover9000,
+                4
+            )
+            .unwrap()
+            .crane()
+            .get_tops()
+        );
+        assert_eq!(
+            "DCP",
+            replay_moves(
+                Crane::from_diagram(diagram).unwrap(),
+                moves,
+                &CrateMover9000,
+                1
+            )
+            .unwrap()
+            .crane()
+            .get_tops()
+        );
+    }
+
+    #[test]
+    fn test_crane_models() {
+        let diagram =
+            "[A]        \n[B]        \n[C]        \n[D]        \n[E] [F]    \n 1   2   3 ";
+        let expected = [
+            (
+                Box::new(CrateMover9000) as Box<dyn CraneModel>,
+                "E",
+                "FABCD",
+            ),
+            (Box::new(CrateMover9001), "E", "FDCBA"),
+            (Box::new(LimitedCrateMover::new(3)), "E", "FCBAD"),
+            (Box::new(LimitedCrateMover::new(1)), "E", "FABCD"),
+        ];
+        for (model, first, second) in expected {
+            let mut crane = Crane::from_diagram(diagram).unwrap();
+            crane.move_crates(4, 1, 2, model.as_ref()).unwrap();
+            assert_eq!(vec![first, second, ""], stacks(&crane), "{}", model.name());
+            // moving onto the same stack is a no-op
+            crane.move_crates(3, 2, 2, model.as_ref()).unwrap();
+            assert_eq!(vec![first, second, ""], stacks(&crane));
+        }
+
+        assert_eq!("CrateMover 9001", parse_model("9001").unwrap().name());
+        assert_eq!(
+            "CrateMover limited to 3",
+            parse_model("limited:3").unwrap().name()
+        );
+        assert!(parse_model("limited:0").is_none());
+        assert!(parse_model("9002").is_none());
+    }
+
+    #[test]
+    fn test_find_moves() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let start = Crane::from_diagram(diagram).unwrap();
+        let moves =
+            "move 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2";
+        let models: [&dyn CraneModel; 2] = [&CrateMover9000, &CrateMover9001];
+        for model in models {
+            let mut target = start.clone();
+            run_moves(&mut target, moves, model, None::<&mut Tracer<Vec<u8>>>).unwrap();
+
+            let found = find_moves(&start, &target, model, 100_000).unwrap();
+            assert!(found.len() <= 4);
+            let mut crane = start.clone();
+            for mv in &found {
+                crane
+                    .move_crates(mv.n_crates, mv.from, mv.to, model)
+                    .unwrap();
+            }
+            assert_eq!(target, crane);
+        }
+
+        let mut target = start.clone();
+        target.move_crates(3, 2, 3, &CrateMover9001).unwrap();
+        let found = find_moves(&start, &target, &CrateMover9001, 100_000).unwrap();
+        assert_eq!(vec![Move::new(3, 2, 3)], found);
+        assert_eq!("move 3 from 2 to 3", found[0].to_string());
+        assert_eq!(Ok(vec![]), find_moves(&start, &start, &CrateMover9000, 1));
+
+        let other =
+            Crane::from_diagram("    [X]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ").unwrap();
+        assert_eq!(
+            Err(SearchError::MismatchedCrates),
+            find_moves(&start, &other, &CrateMover9000, 100)
+        );
+        let other =
+            Crane::from_diagram("    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   4 ").unwrap();
+        assert_eq!(
+            Err(SearchError::MismatchedStacks),
+            find_moves(&start, &other, &CrateMover9000, 100)
+        );
+        let mut target = start.clone();
+        run_moves(
+            &mut target,
+            moves,
+            &CrateMover9000,
+            None::<&mut Tracer<Vec<u8>>>,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(SearchError::LimitReached(3)),
+            find_moves(&start, &target, &CrateMover9000, 3)
+        );
+    }
+
     #[test]
     fn test_parse_move_line() {
         assert_eq!((1, 3, 9), parse_move_line("move 1 from 3 to 9"))
@@ -1,49 +1,417 @@
-use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::fmt;
 use std::fs;
+use std::io::{self, Read};
+use std::process;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum StartType {
     Packet,
     Message,
+    Custom(usize),
 }
 
-fn chars_are_unique(queue: &VecDeque<char>, n_size: usize) -> bool {
-    let set: HashSet<&char> = queue.iter().collect();
-    set.len() == n_size
+impl StartType {
+    fn window(&self) -> usize {
+        match self {
+            StartType::Packet => 4,
+            StartType::Message => 14,
+            StartType::Custom(n_size) => *n_size,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            StartType::Packet => "start-of-packet".to_string(),
+            StartType::Message => "start-of-message".to_string(),
+            StartType::Custom(n_size) => format!("{}-distinct", n_size),
+        }
+    }
 }
 
-fn check_line(line: &str, kind: StartType) -> usize {
-    let n_size: usize = match kind {
-        StartType::Packet => 4,
-        StartType::Message => 14,
-    };
+/// Incremental marker detector, fed one symbol at a time.
+///
+/// Each symbol remembers where it was last seen, and the window start jumps
+/// past the previous occurrence of a repeated symbol, so every symbol is
+/// handled in constant time.
+struct MarkerDetector {
+    n_size: usize,
+    last_seen: [Option<usize>; 256],
+    window_start: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    fn new(n_size: usize) -> MarkerDetector {
+        MarkerDetector {
+            n_size,
+            last_seen: [None; 256],
+            window_start: 0,
+            position: 0,
+        }
+    }
+
+    /// Number of symbols consumed so far.
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Forget the current window, so the next marker cannot share any
+    /// symbols with the ones already read.
+    fn restart(&mut self) {
+        self.window_start = self.position;
+    }
+
+    /// Consume the next symbol, returning whether the last `n_size` symbols
+    /// read are now all distinct.
+    fn push(&mut self, symbol: u8) -> bool {
+        if let Some(previous) = self.last_seen[symbol as usize] {
+            self.window_start = self.window_start.max(previous + 1);
+        }
+        self.last_seen[symbol as usize] = Some(self.position);
+        self.position += 1;
+        self.position - self.window_start >= self.n_size
+    }
+}
+
+/// Whether consecutive markers may share symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overlap {
+    Overlapping,
+    NonOverlapping,
+}
 
-    let mut chars = line.chars();
-    let mut queue: VecDeque<char> = chars.by_ref().take(n_size).collect();
+/// Iterator over every position at which a window of `n_size` distinct
+/// symbols ends, counted as the number of symbols read.
+struct MarkerPositions<I: Iterator<Item = u8>> {
+    symbols: I,
+    detector: MarkerDetector,
+    mode: Overlap,
+}
 
-    let mut starting_char = n_size;
+impl<I: Iterator<Item = u8>> Iterator for MarkerPositions<I> {
+    type Item = usize;
 
-    while !chars_are_unique(&queue, n_size) {
-        let next_char = chars.next().unwrap();
-        queue.pop_front();
-        queue.push_back(next_char);
-        starting_char += 1;
+    fn next(&mut self) -> Option<usize> {
+        for symbol in self.symbols.by_ref() {
+            if self.detector.push(symbol) {
+                if self.mode == Overlap::NonOverlapping {
+                    self.detector.restart();
+                }
+                return Some(self.detector.position());
+            }
+        }
+        None
     }
+}
 
-    starting_char
+fn marker_positions<I: IntoIterator<Item = u8>>(
+    symbols: I,
+    n_size: usize,
+    mode: Overlap,
+) -> MarkerPositions<I::IntoIter> {
+    MarkerPositions {
+        symbols: symbols.into_iter(),
+        detector: MarkerDetector::new(n_size),
+        mode,
+    }
+}
+
+/// Summary of the markers of one window size within a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MarkerReport {
+    n_size: usize,
+    first: Option<usize>,
+    last: Option<usize>,
+    count: usize,
+    /// The longest stretch of consecutive positions at which no marker ends.
+    longest_gap: usize,
+}
+
+impl MarkerReport {
+    fn new(stream: &[u8], n_size: usize, mode: Overlap) -> MarkerReport {
+        let mut report = MarkerReport {
+            n_size,
+            first: None,
+            last: None,
+            count: 0,
+            longest_gap: 0,
+        };
+        for position in marker_positions(stream.iter().copied(), n_size, mode) {
+            let previous = report.last.unwrap_or(0);
+            report.longest_gap = report.longest_gap.max(position - previous - 1);
+            report.first = report.first.or(Some(position));
+            report.last = Some(position);
+            report.count += 1;
+        }
+        let trailing = stream.len() - report.last.unwrap_or(0);
+        report.longest_gap = report.longest_gap.max(trailing);
+        report
+    }
+}
+
+impl fmt::Display for MarkerReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |position: Option<usize>| position.map_or("-".to_string(), |p| p.to_string());
+        write!(
+            f,
+            "window {:>2}: first {}, last {}, count {}, longest run without a marker {}",
+            self.n_size,
+            show(self.first),
+            show(self.last),
+            self.count,
+            self.longest_gap
+        )
+    }
+}
+
+/// Find the first marker of every kind in every line of `reader`, treating
+/// each non-empty line as an independent datastream. The input is read in
+/// fixed-size chunks in a single pass, so it is never held in memory, and
+/// each stream's markers are passed to `report` as soon as they are known:
+/// once all of them are found, or at the end of the line. That way a stream
+/// which never ends still gets its markers reported.
+fn find_markers<R: Read>(
+    mut reader: R,
+    kinds: &[StartType],
+    mut report: impl FnMut(&[Option<usize>]),
+) -> io::Result<()> {
+    let new_stream = || -> (Vec<MarkerDetector>, Vec<Option<usize>>) {
+        let detectors = kinds
+            .iter()
+            .map(|kind| MarkerDetector::new(kind.window()))
+            .collect();
+        let found = kinds
+            .iter()
+            .map(|kind| (kind.window() == 0).then_some(0))
+            .collect();
+        (detectors, found)
+    };
+    let (mut detectors, mut found) = new_stream();
+    let mut in_stream = false;
+    let mut reported = false;
+
+    let mut buffer = [0; 8192];
+    loop {
+        let n_read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n_read) => n_read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for &symbol in &buffer[..n_read] {
+            if symbol == b'\n' || symbol == b'\r' {
+                if in_stream && !reported {
+                    report(&found);
+                }
+                (detectors, found) = new_stream();
+                in_stream = false;
+                reported = false;
+                continue;
+            }
+            in_stream = true;
+            if reported {
+                continue;
+            }
+            for (detector, marker) in detectors.iter_mut().zip(found.iter_mut()) {
+                if marker.is_none() && detector.push(symbol) {
+                    *marker = Some(detector.position());
+                }
+            }
+            if found.iter().all(|marker| marker.is_some()) {
+                report(&found);
+                reported = true;
+            }
+        }
+    }
+    if in_stream && !reported {
+        report(&found);
+    }
+    Ok(())
+}
+
+struct Config {
+    pub filename: String,
+    pub kinds: Vec<StartType>,
+    pub all: bool,
+    pub report: bool,
+    pub mode: Overlap,
+}
+
+impl Config {
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
+        // iter past name
+        args.next();
+
+        let mut filename = "../input.txt".to_string();
+        let mut kinds = vec![];
+        let mut all = false;
+        let mut report = false;
+        let mut mode = Overlap::Overlapping;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--packet" => kinds.push(StartType::Packet),
+                "--message" => kinds.push(StartType::Message),
+                "--window" => {
+                    let n_size = args.next().ok_or("Missing value for --window")?;
+                    let n_size = n_size.parse().map_err(|_| "Invalid value for --window")?;
+                    kinds.push(StartType::Custom(n_size));
+                }
+                "--all" => all = true,
+                "--report" => report = true,
+                "--non-overlapping" => mode = Overlap::NonOverlapping,
+                "-" => filename = arg,
+                _ if arg.starts_with("--") => return Err("Unknown option"),
+                _ => filename = arg,
+            }
+        }
+        if kinds.is_empty() {
+            kinds = vec![StartType::Packet, StartType::Message];
+        }
+        Ok(Config {
+            filename,
+            kinds,
+            all,
+            report,
+            mode,
+        })
+    }
 }
 
 fn main() {
-    let line = fs::read_to_string("../input.txt").expect("Missing input fle");
-    let first_marker = check_line(&line, StartType::Packet);
-    println!("The first marker character is {}", first_marker);
+    let config = Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        process::exit(1);
+    });
 
-    let first_marker = check_line(&line, StartType::Message);
-    println!("The first message marker character is {}", first_marker);
+    if config.all || config.report {
+        // every window size needs its own pass, so load the whole stream
+        let stream = if config.filename == "-" {
+            let mut stream = vec![];
+            io::stdin().read_to_end(&mut stream).map(|_| stream)
+        } else {
+            fs::read(&config.filename)
+        };
+        let stream = stream.unwrap_or_else(|err| {
+            eprintln!("Unable to read {}: {}", config.filename, err);
+            process::exit(1);
+        });
+        let streams: Vec<&[u8]> = stream
+            .split(|&symbol| symbol == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty())
+            .collect();
+        for (idx, line) in streams.iter().enumerate() {
+            if streams.len() > 1 {
+                println!("Stream {}:", idx + 1);
+            }
+            for kind in &config.kinds {
+                if config.all {
+                    let positions =
+                        marker_positions(line.iter().copied(), kind.window(), config.mode);
+                    for position in positions {
+                        println!("{} {}", kind.describe(), position);
+                    }
+                }
+                if config.report {
+                    println!("{}", MarkerReport::new(line, kind.window(), config.mode));
+                }
+            }
+        }
+        return;
+    }
+
+    // `-` reads the datastream from stdin, so markers can be found on a pipe.
+    // The number of streams is not known up front, so only the ones after
+    // the first get a heading.
+    let mut n_streams = 0;
+    let print_stream = |stream: &[Option<usize>]| {
+        n_streams += 1;
+        if n_streams > 1 {
+            println!("Stream {}:", n_streams);
+        }
+        for (kind, marker) in config.kinds.iter().zip(stream) {
+            match marker {
+                Some(first_marker) => println!(
+                    "The first {} marker character is {}",
+                    kind.describe(),
+                    first_marker
+                ),
+                None => println!("No {} marker found", kind.describe()),
+            }
+        }
+    };
+    let result = if config.filename == "-" {
+        find_markers(io::stdin().lock(), &config.kinds, print_stream)
+    } else {
+        fs::File::open(&config.filename)
+            .and_then(|file| find_markers(file, &config.kinds, print_stream))
+    };
+    if let Err(err) = result {
+        eprintln!("Unable to read {}: {}", config.filename, err);
+        process::exit(1);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::{HashSet, VecDeque};
+    use std::time::Instant;
+
+    fn chars_are_unique(queue: &VecDeque<char>, n_size: usize) -> bool {
+        let set: HashSet<&char> = queue.iter().collect();
+        set.len() == n_size
+    }
+
+    // Deterministic pseudo-random stream over the first `alphabet` lowercase letters.
+    fn generate_stream(len: usize, alphabet: u8, mut seed: u64) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b'a' + ((seed >> 33) % alphabet as u64) as u8
+            })
+            .collect()
+    }
+
+    // Number of symbols read when the last `n_size` of them are all distinct
+    // for the first time, straight from a `MarkerDetector` over a slice.
+    fn find_marker(stream: &[u8], n_size: usize) -> Option<usize> {
+        if n_size == 0 {
+            return Some(0);
+        }
+        let mut detector = MarkerDetector::new(n_size);
+        stream
+            .iter()
+            .position(|&symbol| detector.push(symbol))
+            .map(|idx| idx + 1)
+    }
+
+    // Every stream's markers from `find_markers`, in the order reported.
+    fn collect_markers<R: Read>(reader: R, kinds: &[StartType]) -> Vec<Vec<Option<usize>>> {
+        let mut streams = vec![];
+        find_markers(reader, kinds, |markers| streams.push(markers.to_vec())).unwrap();
+        streams
+    }
+
+    // First marker of `kind` in a single line, read through `find_markers`
+    // like `main` does.
+    fn check_line(line: &str, kind: StartType) -> Option<usize> {
+        let streams = collect_markers(line.as_bytes(), &[kind]);
+        assert!(streams.len() <= 1, "{:?} holds several streams", line);
+        streams.first().and_then(|markers| markers[0])
+    }
+
+    // The original approach: slide a queue and rebuild a set at every step.
+    fn find_marker_naive(stream: &[u8], n_size: usize) -> Option<usize> {
+        let chars: Vec<char> = stream.iter().map(|&b| b as char).collect();
+        (n_size..=chars.len()).find(|&end| {
+            let queue: VecDeque<char> = chars[end - n_size..end].iter().copied().collect();
+            chars_are_unique(&queue, n_size)
+        })
+    }
 
     #[test]
     fn test_chars_are_unique() {
@@ -54,38 +422,268 @@ mod tests {
         assert!(!chars_are_unique(&queue, 4));
     }
 
+    // Each row of the table is a stream followed by its expected first
+    // start-of-packet and start-of-message markers, `-` when there is none.
+    fn sample_markers() -> Vec<(String, Option<usize>, Option<usize>)> {
+        include_str!("../../sample_markers.txt")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let columns: Vec<&str> = line.split_whitespace().collect();
+                let marker = |column: &str| column.parse().ok();
+                (
+                    columns[0].to_string(),
+                    marker(columns[1]),
+                    marker(columns[2]),
+                )
+            })
+            .collect()
+    }
+
     #[test]
     fn test_check_line() {
-        let line = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        let first_marker = check_line(line, StartType::Packet);
-        assert_eq!(5, first_marker);
+        for (line, packet, message) in sample_markers() {
+            assert_eq!(packet, check_line(&line, StartType::Packet), "{}", line);
+            assert_eq!(message, check_line(&line, StartType::Message), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_find_markers_per_line() {
+        let table = sample_markers();
+        let streams: Vec<String> = table.iter().map(|row| row.0.clone()).collect();
+        let expected: Vec<Vec<Option<usize>>> =
+            table.iter().map(|row| vec![row.1, row.2]).collect();
+        let kinds = [StartType::Packet, StartType::Message];
+
+        // trailing newlines, blank lines and CRLF endings do not make streams
+        let contents = format!("{}\n\n", streams.join("\r\n"));
+        assert_eq!(expected, collect_markers(contents.as_bytes(), &kinds));
+        let contents = streams.join("\n");
+        assert_eq!(expected, collect_markers(contents.as_bytes(), &kinds));
+        assert!(collect_markers(&b"\n\n"[..], &kinds).is_empty());
+    }
 
-        let line = "nppdvjthqldpwncqszvftbrmjlhg";
-        let first_marker = check_line(line, StartType::Packet);
-        assert_eq!(6, first_marker);
+    #[test]
+    fn test_sample_input() {
+        // the sample file holds the streams of the table, one per line
+        let table = sample_markers();
+        let kinds = [StartType::Packet, StartType::Message];
+        let markers = collect_markers(&include_bytes!("../../sample_input.txt")[..], &kinds);
+        assert_eq!(5, markers.len());
+        for (row, stream) in table.iter().zip(&markers) {
+            assert_eq!(&vec![row.1, row.2], stream, "{}", row.0);
+        }
+    }
+
+    #[test]
+    fn test_find_marker_without_marker() {
+        assert_eq!(None, check_line("abcabcabcabc", StartType::Packet));
+        assert_eq!(None, check_line("ab", StartType::Packet));
+        assert_eq!(None, check_line("", StartType::Message));
+        assert_eq!(Some(0), find_marker(b"", 0));
+        assert_eq!(Some(1), find_marker(b"aaaa", 1));
+    }
+
+    #[test]
+    fn test_find_marker_matches_naive() {
+        for seed in 0..50 {
+            let stream = generate_stream(300, 6 + (seed % 10) as u8, seed);
+            for n_size in 1..=16 {
+                assert_eq!(
+                    find_marker_naive(&stream, n_size),
+                    find_marker(&stream, n_size),
+                    "seed {} window {}",
+                    seed,
+                    n_size
+                );
+            }
+        }
+    }
+
+    // Hands out the wrapped bytes a few at a time, like a slow pipe.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n_read = self.chunk.min(buf.len()).min(self.bytes.len());
+            buf[..n_read].copy_from_slice(&self.bytes[..n_read]);
+            self.bytes = &self.bytes[n_read..];
+            Ok(n_read)
+        }
+    }
 
+    #[test]
+    fn test_find_markers_from_reader() {
         let line = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
-        let first_marker = check_line(line, StartType::Packet);
-        assert_eq!(10, first_marker);
+        let kinds = [
+            StartType::Packet,
+            StartType::Message,
+            StartType::Custom(1),
+            StartType::Custom(40),
+        ];
+        for chunk in [1, 3, 64] {
+            let reader = Trickle {
+                bytes: line.as_bytes(),
+                chunk,
+            };
+            assert_eq!(
+                vec![vec![Some(10), Some(29), Some(1), None]],
+                collect_markers(reader, &kinds)
+            );
+        }
+
+        let stream = generate_stream(100_000, 16, 3);
+        for n_size in [0, 4, 9, 14] {
+            let reader = Trickle {
+                bytes: &stream,
+                chunk: 1000,
+            };
+            assert_eq!(
+                vec![vec![find_marker(&stream, n_size)]],
+                collect_markers(reader, &[StartType::Custom(n_size)])
+            );
+        }
+        assert_eq!(
+            Some(5),
+            check_line("bvwbjplbgvbhsrlpgdmjqwftvncz", StartType::Custom(4))
+        );
+    }
+
+    // Hands out the wrapped bytes, then fails like a pipe that broke.
+    struct Broken<'a>(&'a [u8]);
+
+    impl Read for Broken<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("broken pipe"));
+            }
+            let n_read = buf.len().min(self.0.len());
+            buf[..n_read].copy_from_slice(&self.0[..n_read]);
+            self.0 = &self.0[n_read..];
+            Ok(n_read)
+        }
+    }
+
+    #[test]
+    fn test_find_markers_before_end() {
+        // streams are reported as their line ends, or as soon as all their
+        // markers are found, without waiting for the input to end
+        let kinds = [StartType::Packet, StartType::Message];
+        let mut streams = vec![];
+        let reader = Broken(b"abcabc\nmjqjpqmgbljsphdztnvjfqwrcgsmlbabab");
+        let result = find_markers(reader, &kinds, |markers| streams.push(markers.to_vec()));
+        assert!(result.is_err());
+        assert_eq!(vec![vec![None, None], vec![Some(7), Some(19)]], streams);
+
+        // the rest of a finished stream does not report it again
+        let contents = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\nabc\n";
+        assert_eq!(
+            vec![vec![Some(7), Some(19)], vec![None, None]],
+            collect_markers(contents.as_bytes(), &kinds)
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let args = ["bin", "--window", "7", "--packet", "-"];
+        let config = Config::new(args.iter().map(|s| s.to_string())).unwrap();
+        assert_eq!(vec![StartType::Custom(7), StartType::Packet], config.kinds);
+        assert_eq!("-", config.filename);
+
+        let config = Config::new(["bin"].iter().map(|s| s.to_string())).unwrap();
+        assert_eq!(vec![StartType::Packet, StartType::Message], config.kinds);
+        assert_eq!("../input.txt", config.filename);
 
-        let line = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        let first_marker = check_line(line, StartType::Packet);
-        assert_eq!(11, first_marker);
+        let args = ["bin", "--report", "--non-overlapping"];
+        let config = Config::new(args.iter().map(|s| s.to_string())).unwrap();
+        assert!(config.report && !config.all);
+        assert_eq!(Overlap::NonOverlapping, config.mode);
+
+        let args = ["bin", "--window", "four"];
+        assert!(Config::new(args.iter().map(|s| s.to_string())).is_err());
+    }
+
+    #[test]
+    fn test_marker_positions() {
+        let stream = b"abcabcaab";
+        let overlapping: Vec<usize> =
+            marker_positions(stream.iter().copied(), 3, Overlap::Overlapping).collect();
+        assert_eq!(vec![3, 4, 5, 6, 7], overlapping);
+
+        let non_overlapping: Vec<usize> =
+            marker_positions(stream.iter().copied(), 3, Overlap::NonOverlapping).collect();
+        assert_eq!(vec![3, 6], non_overlapping);
 
         let line = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        let first_marker = check_line(line, StartType::Message);
-        assert_eq!(23, first_marker);
+        let first = marker_positions(line.bytes(), 4, Overlap::Overlapping).next();
+        assert_eq!(check_line(line, StartType::Packet), first);
+
+        for seed in 0..20 {
+            let stream = generate_stream(200, 8, seed);
+            let expected: Vec<usize> = (4..=stream.len())
+                .filter(|&end| find_marker(&stream[end - 4..end], 4).is_some())
+                .collect();
+            let positions: Vec<usize> =
+                marker_positions(stream.iter().copied(), 4, Overlap::Overlapping).collect();
+            assert_eq!(expected, positions);
+        }
+    }
 
-        let line = "nppdvjthqldpwncqszvftbrmjlhg";
-        let first_marker = check_line(line, StartType::Message);
-        assert_eq!(23, first_marker);
+    #[test]
+    fn test_marker_report() {
+        let report = MarkerReport::new(b"aabcabcaabbbb", 3, Overlap::Overlapping);
+        assert_eq!(
+            MarkerReport {
+                n_size: 3,
+                first: Some(4),
+                last: Some(8),
+                count: 5,
+                longest_gap: 5
+            },
+            report
+        );
+        assert_eq!(
+            "window  3: first 4, last 8, count 5, longest run without a marker 5",
+            report.to_string()
+        );
 
-        let line = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
-        let first_marker = check_line(line, StartType::Message);
-        assert_eq!(29, first_marker);
+        let report = MarkerReport::new(b"aabcabcaabbbb", 3, Overlap::NonOverlapping);
+        assert_eq!(
+            (Some(4), Some(7), 2),
+            (report.first, report.last, report.count)
+        );
 
-        let line = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        let first_marker = check_line(line, StartType::Message);
-        assert_eq!(26, first_marker);
+        let report = MarkerReport::new(b"aaaa", 2, Overlap::Overlapping);
+        assert_eq!(
+            (None, None, 0, 4),
+            (report.first, report.last, report.count, report.longest_gap)
+        );
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_find_markers() {
+        for megabytes in [1, 8, 32] {
+            // a small alphabet keeps wide windows from matching early
+            let stream = generate_stream(megabytes << 20, 20, 7);
+            for n_size in [4, 14, 20] {
+                let start = Instant::now();
+                let kinds = [StartType::Custom(n_size)];
+                let mut marker = None;
+                find_markers(&stream[..], &kinds, |markers| marker = markers[0]).unwrap();
+                println!(
+                    "{:>3} MiB, window {:>2}: {:?} in {:?}",
+                    megabytes,
+                    n_size,
+                    marker,
+                    start.elapsed()
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,296 @@
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{is_valid_name, FileMetaData, FileSystem, NodeId};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FileJson {
+    name: String,
+    size: u64,
+}
+
+/// JSON shape of a directory. On import `path` is ignored and `size`, when
+/// present, is checked against the size computed from the files.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct DirJson {
+    name: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default = "default_listed")]
+    listed: bool,
+    #[serde(default)]
+    files: Vec<FileJson>,
+    #[serde(default)]
+    dirs: Vec<DirJson>,
+}
+
+// hand-written trees describe every directory completely
+fn default_listed() -> bool {
+    true
+}
+
+#[derive(Debug)]
+pub enum JsonError {
+    Parse(serde_json::Error),
+    /// Two entries of one directory share a name.
+    Duplicate {
+        path: String,
+        name: String,
+    },
+    /// An entry whose name cannot appear in a path, such as `..` or `a b`.
+    InvalidName {
+        path: String,
+        name: String,
+    },
+    /// The top-level directory is not named `/`.
+    RootName(String),
+    /// A directory marked as never listed holds files, which no session
+    /// could have shown.
+    UnlistedFiles(String),
+    /// A directory declares a size which does not match its contents.
+    SizeMismatch {
+        path: String,
+        declared: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Parse(err) => write!(f, "invalid JSON: {}", err),
+            JsonError::Duplicate { path, name } => {
+                write!(f, "{} holds more than one entry named {}", path, name)
+            }
+            JsonError::InvalidName { path, name } => {
+                write!(f, "{} holds an entry with invalid name {:?}", path, name)
+            }
+            JsonError::RootName(name) => {
+                write!(f, "the root directory must be named /, not {:?}", name)
+            }
+            JsonError::UnlistedFiles(path) => {
+                write!(f, "{} holds files but was never listed", path)
+            }
+            JsonError::SizeMismatch {
+                path,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "{} declares size {} but its contents add up to {}",
+                path, declared, actual
+            ),
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+fn dir_to_json(tree: &FileSystem, id: NodeId) -> DirJson {
+    let node = &tree.nodes[id];
+    DirJson {
+        name: node.name.clone(),
+        path: tree.path(id),
+        size: Some(tree.get_size(id).0),
+        listed: node.listed,
+        files: node
+            .files
+            .iter()
+            .map(|f| FileJson {
+                name: f.name.clone(),
+                size: f.size,
+            })
+            .collect(),
+        dirs: node
+            .children
+            .iter()
+            .map(|&child| dir_to_json(tree, child))
+            .collect(),
+    }
+}
+
+/// Serialize the whole tree, with full paths and cumulative directory sizes.
+pub fn to_json(tree: &FileSystem) -> String {
+    serde_json::to_string_pretty(&dir_to_json(tree, FileSystem::ROOT))
+        .expect("a tree always serializes")
+}
+
+fn add_json_dir(tree: &mut FileSystem, id: NodeId, dir: &DirJson) -> Result<(), JsonError> {
+    tree.nodes[id].listed = dir.listed;
+    if !dir.listed && !dir.files.is_empty() {
+        return Err(JsonError::UnlistedFiles(tree.path(id)));
+    }
+    let names = dir
+        .files
+        .iter()
+        .map(|f| &f.name)
+        .chain(dir.dirs.iter().map(|d| &d.name));
+    if let Some(name) = names.into_iter().find(|name| !is_valid_name(name)) {
+        return Err(JsonError::InvalidName {
+            path: tree.path(id),
+            name: name.clone(),
+        });
+    }
+    for file in &dir.files {
+        let name = file.name.clone();
+        let file = FileMetaData {
+            name: file.name.clone(),
+            size: file.size,
+        };
+        if tree.file(id, &name).is_some() || tree.add_file(id, file).is_err() {
+            return Err(JsonError::Duplicate {
+                path: tree.path(id),
+                name,
+            });
+        }
+    }
+    for child in &dir.dirs {
+        if tree.child(id, &child.name).is_some() || tree.file(id, &child.name).is_some() {
+            return Err(JsonError::Duplicate {
+                path: tree.path(id),
+                name: child.name.clone(),
+            });
+        }
+        let child_id = tree.add_dir(id, child.name.clone());
+        add_json_dir(tree, child_id, child)?;
+    }
+    Ok(())
+}
+
+fn check_sizes(tree: &FileSystem, id: NodeId, dir: &DirJson) -> Result<(), JsonError> {
+    let actual = tree.get_size(id).0;
+    if let Some(declared) = dir.size.filter(|&declared| declared != actual) {
+        return Err(JsonError::SizeMismatch {
+            path: tree.path(id),
+            declared,
+            actual,
+        });
+    }
+    for child in &dir.dirs {
+        check_sizes(tree, tree.child(id, &child.name).unwrap(), child)?;
+    }
+    Ok(())
+}
+
+/// Rebuild a tree from the output of `to_json`, or from a hand-written
+/// description in which only names, files and sub-directories are required.
+pub fn from_json(text: &str) -> Result<FileSystem, JsonError> {
+    let root: DirJson = serde_json::from_str(text).map_err(JsonError::Parse)?;
+    if root.name != "/" {
+        return Err(JsonError::RootName(root.name));
+    }
+    let mut tree = FileSystem::new();
+    add_json_dir(&mut tree, FileSystem::ROOT, &root)?;
+    tree.update_sizes();
+    check_sizes(&tree, FileSystem::ROOT, &root)?;
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tree;
+    use crate::report::render_tree;
+    use crate::report::{du, SortBy};
+
+    #[test]
+    fn test_round_trip() {
+        let tree = build_tree(include_str!("../../sample_input.txt")).unwrap();
+        let json = to_json(&tree);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(48381165, value["size"]);
+        assert_eq!("/a/e", value["dirs"][0]["dirs"][0]["path"]);
+        assert_eq!(584, value["dirs"][0]["dirs"][0]["size"]);
+        assert_eq!("i", value["dirs"][0]["dirs"][0]["files"][0]["name"]);
+
+        let loaded = from_json(&json).unwrap();
+        assert_eq!(render_tree(&tree, None), render_tree(&loaded, None));
+        assert_eq!(json, to_json(&loaded));
+    }
+
+    #[test]
+    fn test_hand_written() {
+        let json = r#"{
+            "name": "/",
+            "files": [{ "name": "f", "size": 10 }],
+            "dirs": [
+                { "name": "a", "files": [{ "name": "g", "size": 5 }] },
+                { "name": "b", "listed": false }
+            ]
+        }"#;
+        let tree = from_json(json).unwrap();
+        assert_eq!(
+            vec![
+                (15, "/".to_string()),
+                (5, "/a".to_string()),
+                (0, "/b".to_string())
+            ],
+            du(&tree, SortBy::Size, None)
+        );
+        assert_eq!(1, tree.unexplored_dirs().len());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let json = r#"{ "name": "/", "dirs": [
+            { "name": "a", "listed": false, "files": [{ "name": "f", "size": 1 }] }
+        ] }"#;
+        assert_eq!(
+            "/a holds files but was never listed",
+            from_json(json).unwrap_err().to_string()
+        );
+
+        let json = r#"{ "name": "/", "size": 3, "files": [{ "name": "f", "size": 10 }] }"#;
+        assert_eq!(
+            "/ declares size 3 but its contents add up to 10",
+            from_json(json).unwrap_err().to_string()
+        );
+
+        let json = r#"{ "name": "/", "dirs": [{ "name": "a", "size": 4 }] }"#;
+        assert_eq!(
+            "/a declares size 4 but its contents add up to 0",
+            from_json(json).unwrap_err().to_string()
+        );
+
+        let json =
+            r#"{ "name": "/", "files": [{ "name": "f", "size": 10 }], "dirs": [{ "name": "f" }] }"#;
+        assert_eq!(
+            "/ holds more than one entry named f",
+            from_json(json).unwrap_err().to_string()
+        );
+
+        let json = r#"{ "name": "/", "dirs": [{ "name": "a", "files": [{ "name": "f", "size": 1 }, { "name": "f", "size": 1 }] }] }"#;
+        assert_eq!(
+            "/a holds more than one entry named f",
+            from_json(json).unwrap_err().to_string()
+        );
+
+        for name in ["", ".", "..", "a/b", "a b", "tab\there", "-x"] {
+            let json = format!(
+                r#"{{ "name": "/", "dirs": [{{ "name": "d", "files": [{{ "name": {:?}, "size": 1 }}] }}] }}"#,
+                name
+            );
+            assert_eq!(
+                format!("/d holds an entry with invalid name {:?}", name),
+                from_json(&json).unwrap_err().to_string()
+            );
+            let json = format!(r#"{{ "name": "/", "dirs": [{{ "name": {:?} }}] }}"#, name);
+            assert!(matches!(
+                from_json(&json),
+                Err(JsonError::InvalidName { .. })
+            ));
+        }
+
+        let json = r#"{ "name": "root", "files": [{ "name": "f", "size": 10 }] }"#;
+        assert_eq!(
+            "the root directory must be named /, not \"root\"",
+            from_json(json).unwrap_err().to_string()
+        );
+
+        assert!(matches!(from_json("[1, 2]"), Err(JsonError::Parse(_))));
+    }
+}
@@ -1,16 +1,53 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::path::Path;
+use std::process;
+
+mod json;
+mod planner;
+mod query;
+mod report;
+mod transcript;
+
+use planner::CleanupPlanner;
+use query::Query;
+use report::SortBy;
 
 #[derive(Debug, PartialEq)]
 enum CommandKind {
     ChangeDir,
     ListDir,
+    Remove,
+    MakeDir,
+    Touch,
+    Move,
     Unk,
 }
 
 #[derive(Debug, PartialEq)]
 struct Command {
     kind: CommandKind,
-    argument: Option<String>,
+    /// Everything after the command name, flags included.
+    arguments: Vec<String>,
+}
+
+impl Command {
+    fn flags(&self) -> String {
+        self.arguments
+            .iter()
+            .filter_map(|arg| arg.strip_prefix('-'))
+            .collect()
+    }
+
+    fn paths(&self) -> Vec<&str> {
+        self.arguments
+            .iter()
+            .filter(|arg| !arg.starts_with('-'))
+            .map(|arg| arg.as_str())
+            .collect()
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -38,149 +75,748 @@ enum Line {
     Empty,
 }
 
-fn starts_with_number(line: &str) -> bool {
-    line.chars().nth(0).unwrap().is_numeric()
+/// Whether `name` can be a single path component in a terminal session:
+/// not empty, not `.` or `..`, not starting with the `-` of a flag, and free
+/// of `/` and of the whitespace which `parse_line` splits on.
+fn is_valid_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..")
+        && !name.starts_with('-')
+        && !name.contains(|c: char| c == '/' || c.is_whitespace())
 }
 
-use std::cell::RefCell;
-use std::rc::Rc;
+/// Index of a node in a `FileSystem` arena.
+type NodeId = usize;
 
 #[derive(Debug, PartialEq)]
 struct TreeNode {
     pub name: String,
     pub files: Vec<FileMetaData>,
-    pub childern: Vec<Rc<RefCell<TreeNode>>>,
-    pub parent: Option<Rc<RefCell<TreeNode>>>,
+    pub children: Vec<NodeId>,
+    pub parent: Option<NodeId>,
+    /// Whether `ls` has been run in this directory. Directories only known
+    /// from a listing or a `cd` may hold files the session never showed.
+    pub listed: bool,
+    /// Cumulative size of everything below this directory, kept up to date
+    /// by `FileSystem::update_sizes`.
+    size: u64,
 }
 
 impl TreeNode {
-    pub fn new(name: String) -> TreeNode {
+    pub fn new(name: String, parent: Option<NodeId>) -> TreeNode {
         TreeNode {
             name,
             files: vec![],
-            childern: vec![],
-            parent: None,
+            children: vec![],
+            parent,
+            listed: false,
+            size: 0,
+        }
+    }
+}
+
+/// Directory tree stored as an arena of nodes linked by index, with the
+/// root directory `/` at index 0.
+#[derive(Debug, PartialEq)]
+struct FileSystem {
+    nodes: Vec<TreeNode>,
+}
+
+impl FileSystem {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new() -> FileSystem {
+        FileSystem {
+            nodes: vec![TreeNode::new("/".to_string(), None)],
+        }
+    }
+
+    pub fn add_dir(&mut self, parent: NodeId, name: String) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(TreeNode::new(name, Some(parent)));
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        self.nodes[dir]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.nodes[c].name == name)
+    }
+
+    pub fn file(&self, dir: NodeId, name: &str) -> Option<&FileMetaData> {
+        self.nodes[dir].files.iter().find(|f| f.name == name)
+    }
+
+    /// Record a directory seen in a listing, unless it is already known.
+    pub fn add_dir_entry(&mut self, dir: NodeId, name: String) -> Result<NodeId, SessionErrorKind> {
+        if self.file(dir, &name).is_some() {
+            return Err(SessionErrorKind::NotADirectory(name));
+        }
+        match self.child(dir, &name) {
+            Some(child) => Ok(child),
+            None => Ok(self.add_dir(dir, name)),
+        }
+    }
+
+    /// Full path of a directory, e.g. `/a/e`.
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            names.push(self.nodes[current].name.as_str());
+            current = parent;
         }
+        names.reverse();
+        format!("/{}", names.join("/"))
     }
 
-    pub fn get_size(&self) -> (u64, String) {
-        let mut size = 0;
-        for f in &self.files {
-            size += f.size;
+    /// Directories whose contents were never listed, so that their sizes
+    /// (and those of their parents) may be too small.
+    pub fn unexplored_dirs(&self) -> Vec<NodeId> {
+        self.pre_order()
+            .into_iter()
+            .filter(|&id| !self.nodes[id].listed)
+            .collect()
+    }
+
+    /// Record a listed file, ignoring it if the directory already holds the
+    /// same file from an earlier listing.
+    pub fn add_file(&mut self, dir: NodeId, file: FileMetaData) -> Result<(), SessionErrorKind> {
+        if self.child(dir, &file.name).is_some() {
+            return Err(SessionErrorKind::NotAFile(file.name));
+        }
+        match self.file(dir, &file.name) {
+            Some(existing) if existing.size == file.size => Ok(()),
+            Some(existing) => Err(SessionErrorKind::ConflictingSize {
+                name: file.name,
+                listed: existing.size,
+                relisted: file.size,
+            }),
+            None => {
+                self.nodes[dir].files.push(file);
+                Ok(())
+            }
+        }
+    }
+
+    /// Follow `path` from `current`, the way `cd` would. Absolute paths start
+    /// at the root; directories which do not exist yet are created.
+    pub fn change_dir(&mut self, current: NodeId, path: &str) -> Result<NodeId, SessionErrorKind> {
+        let mut dir = if path.starts_with('/') {
+            FileSystem::ROOT
+        } else {
+            current
+        };
+        for name in path
+            .split('/')
+            .filter(|name| !name.is_empty() && *name != ".")
+        {
+            dir = match name {
+                ".." => self.nodes[dir].parent.ok_or(SessionErrorKind::AboveRoot)?,
+                _ => match self.child(dir, name) {
+                    Some(child) => child,
+                    None if self.file(dir, name).is_some() => {
+                        return Err(SessionErrorKind::NotADirectory(name.to_string()))
+                    }
+                    None => self.add_dir(dir, name.to_string()),
+                },
+            };
+        }
+        Ok(dir)
+    }
+
+    /// Follow `path` from `current` without creating anything.
+    pub fn find_dir(&self, current: NodeId, path: &str) -> Result<NodeId, SessionErrorKind> {
+        let mut dir = if path.starts_with('/') {
+            FileSystem::ROOT
+        } else {
+            current
+        };
+        for name in path
+            .split('/')
+            .filter(|name| !name.is_empty() && *name != ".")
+        {
+            dir = match name {
+                ".." => self.nodes[dir].parent.ok_or(SessionErrorKind::AboveRoot)?,
+                _ => match self.child(dir, name) {
+                    Some(child) => child,
+                    None if self.file(dir, name).is_some() => {
+                        return Err(SessionErrorKind::NotADirectory(name.to_string()))
+                    }
+                    None => return Err(SessionErrorKind::NoSuchEntry(path.to_string())),
+                },
+            };
         }
-        for child in &self.childern {
-            let (s, _) = child.borrow_mut().get_size();
-            size += s
-            //size += child.borrow_mut().get_size();
+        Ok(dir)
+    }
+
+    /// The existing directory holding the last component of `path`, and the
+    /// name of that component.
+    pub fn locate(
+        &self,
+        current: NodeId,
+        path: &str,
+    ) -> Result<(NodeId, String), SessionErrorKind> {
+        let trimmed = path.trim_end_matches('/');
+        let (parent, name) = match trimmed.rsplit_once('/') {
+            Some(("", name)) => ("/", name),
+            Some((parent, name)) => (parent, name),
+            None => (".", trimmed),
+        };
+        if matches!(name, "" | "." | "..") {
+            return Err(SessionErrorKind::InvalidTarget(path.to_string()));
+        }
+        Ok((self.find_dir(current, parent)?, name.to_string()))
+    }
+
+    /// Delete a file, or a whole directory when `recursive` is set, the way
+    /// `rm` would. Returns the directory to carry on in: `current`, or the
+    /// parent of the removed directory if `current` was inside it.
+    pub fn remove(
+        &mut self,
+        current: NodeId,
+        path: &str,
+        recursive: bool,
+    ) -> Result<NodeId, SessionErrorKind> {
+        let (dir, name) = self.locate(current, path)?;
+        if let Some(child) = self.child(dir, &name) {
+            if !recursive {
+                return Err(SessionErrorKind::NotAFile(name));
+            }
+            // the node stays in the arena but is no longer reachable
+            self.nodes[dir].children.retain(|&c| c != child);
+            let inside = std::iter::successors(Some(current), |&id| self.nodes[id].parent)
+                .any(|id| id == child);
+            return Ok(if inside { dir } else { current });
+        }
+        let files = &mut self.nodes[dir].files;
+        match files.iter().position(|f| f.name == name) {
+            Some(idx) => {
+                files.remove(idx);
+                Ok(current)
+            }
+            None => Err(SessionErrorKind::NoSuchEntry(path.to_string())),
         }
-        (size, self.name.clone())
+    }
+
+    /// Rename or move a file or directory, the way `mv` would: moving onto
+    /// an existing directory puts the source inside it, and a moved file
+    /// replaces a file of the same name.
+    pub fn move_entry(
+        &mut self,
+        current: NodeId,
+        from: &str,
+        to: &str,
+    ) -> Result<(), SessionErrorKind> {
+        let (source_dir, source_name) = self.locate(current, from)?;
+        let (target_dir, target_name) = match self.find_dir(current, to) {
+            Ok(dir) => (dir, source_name.clone()),
+            Err(SessionErrorKind::NoSuchEntry(_)) | Err(SessionErrorKind::NotADirectory(_)) => {
+                self.locate(current, to)?
+            }
+            Err(err) => return Err(err),
+        };
+        if self.child(target_dir, &target_name).is_some() {
+            return Err(SessionErrorKind::AlreadyExists(to.to_string()));
+        }
+
+        if let Some(child) = self.child(source_dir, &source_name) {
+            if self.file(target_dir, &target_name).is_some() {
+                return Err(SessionErrorKind::NotADirectory(target_name));
+            }
+            let mut ancestor = Some(target_dir);
+            while let Some(id) = ancestor {
+                if id == child {
+                    return Err(SessionErrorKind::MoveIntoItself(from.to_string()));
+                }
+                ancestor = self.nodes[id].parent;
+            }
+            self.nodes[source_dir].children.retain(|&c| c != child);
+            self.nodes[target_dir].children.push(child);
+            self.nodes[child].parent = Some(target_dir);
+            self.nodes[child].name = target_name;
+            return Ok(());
+        }
+
+        let files = &mut self.nodes[source_dir].files;
+        let idx = files
+            .iter()
+            .position(|f| f.name == source_name)
+            .ok_or(SessionErrorKind::NoSuchEntry(from.to_string()))?;
+        let mut file = files.remove(idx);
+        file.name = target_name;
+        let files = &mut self.nodes[target_dir].files;
+        files.retain(|f| f.name != file.name);
+        files.push(file);
+        // as with `touch`, a directory holding a file counts as listed
+        self.nodes[target_dir].listed = true;
+        Ok(())
+    }
+
+    /// Every directory reachable from the root, parents before children.
+    pub fn pre_order(&self) -> Vec<NodeId> {
+        let mut order = vec![];
+        let mut stack = vec![FileSystem::ROOT];
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.nodes[id].children.iter().rev());
+        }
+        order
+    }
+
+    /// Recompute every cached directory size in one post-order pass.
+    pub fn update_sizes(&mut self) {
+        for id in self.pre_order().into_iter().rev() {
+            let node = &self.nodes[id];
+            let files: u64 = node.files.iter().map(|f| f.size).sum();
+            let children: u64 = node.children.iter().map(|&c| self.nodes[c].size).sum();
+            self.nodes[id].size = files + children;
+        }
+    }
+
+    pub fn get_size(&self, id: NodeId) -> (u64, String) {
+        let node = &self.nodes[id];
+        (node.size, node.name.clone())
     }
 }
 
-fn parse_line(line: &str) -> Line {
+fn parse_line(line: &str) -> Result<Line, SessionErrorKind> {
     let split_line: Vec<_> = line.split_whitespace().collect();
 
     if line.starts_with('$') {
-        let kind = match split_line.get(1).unwrap() {
-            &"cd" => CommandKind::ChangeDir,
-            &"ls" => CommandKind::ListDir,
+        let kind = match split_line.get(1).copied() {
+            Some("cd") => CommandKind::ChangeDir,
+            Some("ls") => CommandKind::ListDir,
+            Some("rm") => CommandKind::Remove,
+            Some("mkdir") => CommandKind::MakeDir,
+            Some("touch") => CommandKind::Touch,
+            Some("mv") => CommandKind::Move,
             _ => CommandKind::Unk,
         };
-        let argument = split_line.get(2).map(|s| s.to_string());
+        let arguments = split_line.iter().skip(2).map(|s| s.to_string()).collect();
 
-        Line::Command(Command { kind, argument })
-    } else if line.starts_with("dir") {
-        let name = split_line.last().unwrap().to_string();
-        Line::FileSystemEntity(FileSystemEntity::Dir(DirMetaData {
-            name,
-            contains: vec![],
-        }))
-    } else if starts_with_number(&line) {
-        let size = split_line.get(0).unwrap().parse().unwrap();
-        let name = split_line.get(1).unwrap().to_string();
-        Line::FileSystemEntity(FileSystemEntity::File(FileMetaData { name, size }))
-    } else {
-        Line::Empty
-    }
-}
-
-fn build_tree(contents: &str) -> Rc<RefCell<TreeNode>> {
-    let mut lines = contents.lines();
-
-    // get the initial cd / command
-    let _ = parse_line(lines.next().unwrap());
-
-    //Line::Command(intial) = parse_line(lines.next().unwrap())
-    let root = Rc::new(RefCell::new(TreeNode::new("/".to_string())));
-    let mut current = Rc::clone(&root);
-
-    for line in lines {
-        let line = parse_line(line);
-        match line {
-            Line::Command(command) => match command.kind {
-                CommandKind::ChangeDir => {
-                    let name = command.argument.unwrap().clone();
-                    if name == ".." {
-                        let current_clone = Rc::clone(&current);
-                        current = Rc::clone(current_clone.borrow_mut().parent.as_ref().unwrap());
-                    } else {
-                        let child = Rc::new(RefCell::new(TreeNode::new(name)));
-                        current.borrow_mut().childern.push(Rc::clone(&child));
-                        {
-                            let mut mut_child = child.borrow_mut();
-                            mut_child.parent = Some(Rc::clone(&current));
-                        }
-                        current = child;
-                    }
-                }
-                CommandKind::ListDir | CommandKind::Unk => {}
-            },
+        return Ok(Line::Command(Command { kind, arguments }));
+    }
+    let invalid = || SessionErrorKind::InvalidLine(line.to_string());
+    match split_line[..] {
+        [] => Ok(Line::Empty),
+        ["dir", name] if is_valid_name(name) => {
+            Ok(Line::FileSystemEntity(FileSystemEntity::Dir(DirMetaData {
+                name: name.to_string(),
+                contains: vec![],
+            })))
+        }
+        [size, name] if is_valid_name(name) => {
+            let size = size.parse().map_err(|_| invalid())?;
+            let name = name.to_string();
+            Ok(Line::FileSystemEntity(FileSystemEntity::File(
+                FileMetaData { name, size },
+            )))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SessionErrorKind {
+    /// The usage of a command which was given too few paths.
+    MissingArgument(&'static str),
+    UnknownCommand,
+    AboveRoot,
+    NotADirectory(String),
+    NotAFile(String),
+    NoSuchEntry(String),
+    AlreadyExists(String),
+    /// A path such as `/` or `..` which names no entry that can be changed.
+    InvalidTarget(String),
+    MoveIntoItself(String),
+    /// A line which is neither a command nor an entry of a listing.
+    InvalidLine(String),
+    ConflictingSize {
+        name: String,
+        listed: u64,
+        relisted: u64,
+    },
+}
+
+/// A terminal session line which cannot be applied to the tree built so far.
+#[derive(Debug, PartialEq)]
+struct SessionError {
+    /// 1-based line number in the session.
+    line: usize,
+    kind: SessionErrorKind,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            SessionErrorKind::MissingArgument(usage) => write!(f, "usage: {}", usage),
+            SessionErrorKind::UnknownCommand => write!(f, "unknown command"),
+            SessionErrorKind::AboveRoot => write!(f, "cannot cd above /"),
+            SessionErrorKind::NotADirectory(name) => write!(f, "{} is a file", name),
+            SessionErrorKind::NotAFile(name) => write!(f, "{} is a directory", name),
+            SessionErrorKind::NoSuchEntry(path) => write!(f, "{} does not exist", path),
+            SessionErrorKind::AlreadyExists(path) => write!(f, "{} already exists", path),
+            SessionErrorKind::InvalidTarget(path) => write!(f, "cannot change {}", path),
+            SessionErrorKind::MoveIntoItself(path) => {
+                write!(f, "cannot move {} into itself", path)
+            }
+            SessionErrorKind::InvalidLine(line) => {
+                write!(f, "{:?} is neither a command nor a listing entry", line)
+            }
+            SessionErrorKind::ConflictingSize {
+                name,
+                listed,
+                relisted,
+            } => write!(
+                f,
+                "{} was listed with size {} and now with size {}",
+                name, listed, relisted
+            ),
+        }
+    }
+}
+
+impl Error for SessionError {}
+
+fn build_tree(contents: &str) -> Result<FileSystem, SessionError> {
+    let mut tree = FileSystem::new();
+    apply_session(&mut tree, contents)?;
+    Ok(tree)
+}
+
+/// Run a terminal session against an existing tree, starting in `/`.
+fn apply_session(tree: &mut FileSystem, contents: &str) -> Result<(), SessionError> {
+    let mut current = FileSystem::ROOT;
+
+    for (idx, line) in contents.lines().enumerate() {
+        let error = |kind| SessionError {
+            line: idx + 1,
+            kind,
+        };
+        match parse_line(line).map_err(error)? {
+            Line::Command(command) => {
+                current = run_command(tree, current, &command).map_err(error)?;
+            }
             Line::FileSystemEntity(entity) => match entity {
                 FileSystemEntity::File(file_data) => {
-                    current.borrow_mut().files.push(file_data);
+                    tree.add_file(current, file_data).map_err(error)?;
+                }
+                FileSystemEntity::Dir(dir_data) => {
+                    tree.add_dir_entry(current, dir_data.name).map_err(error)?;
                 }
-                FileSystemEntity::Dir(_) => {}
             },
             Line::Empty => {}
         }
     }
-    root
+    tree.update_sizes();
+    Ok(())
 }
 
+/// Apply a command to the tree, returning the new current directory.
+fn run_command(
+    tree: &mut FileSystem,
+    current: NodeId,
+    command: &Command,
+) -> Result<NodeId, SessionErrorKind> {
+    let flags = command.flags();
+    let paths = command.paths();
+    let require = |count: usize, usage| {
+        if paths.len() < count {
+            Err(SessionErrorKind::MissingArgument(usage))
+        } else {
+            Ok(())
+        }
+    };
 
-fn get_sizes(tree: &Rc<RefCell<TreeNode>>, sizes: &mut Vec<(u64, String)>) -> () {
-    sizes.push(tree.borrow_mut().get_size());
-    if tree.borrow_mut().childern.len() == 0 {
-        return ();
-    } else {
-        //get size of each child
-        for c in &tree.borrow_mut().childern {
-            get_sizes(c, sizes)
+    match command.kind {
+        CommandKind::ListDir => tree.nodes[current].listed = true,
+        CommandKind::Unk => return Err(SessionErrorKind::UnknownCommand),
+        CommandKind::ChangeDir => {
+            require(1, "cd <path>")?;
+            return tree.change_dir(current, paths[0]);
+        }
+        CommandKind::Remove => {
+            require(1, "rm [-rf] <path>")?;
+            let force = flags.contains('f');
+            match tree.remove(current, paths[0], flags.contains('r')) {
+                Err(SessionErrorKind::NoSuchEntry(_)) if force => {}
+                result => return result,
+            }
+        }
+        CommandKind::MakeDir => {
+            require(1, "mkdir [-p] <path>")?;
+            if flags.contains('p') {
+                let known = tree.nodes.len();
+                tree.change_dir(current, paths[0])?;
+                // new directories are known to be empty
+                for node in &mut tree.nodes[known..] {
+                    node.listed = true;
+                }
+            } else {
+                let (dir, name) = tree.locate(current, paths[0])?;
+                if tree.child(dir, &name).is_some() || tree.file(dir, &name).is_some() {
+                    return Err(SessionErrorKind::AlreadyExists(paths[0].to_string()));
+                }
+                let id = tree.add_dir(dir, name);
+                tree.nodes[id].listed = true;
+            }
+        }
+        CommandKind::Touch => {
+            require(1, "touch <path>")?;
+            let (dir, name) = tree.locate(current, paths[0])?;
+            if tree.child(dir, &name).is_none() && tree.file(dir, &name).is_none() {
+                tree.nodes[dir].files.push(FileMetaData { name, size: 0 });
+                // a transcript shows files only in listings, so a directory
+                // holding one counts as listed
+                tree.nodes[dir].listed = true;
+            }
+        }
+        CommandKind::Move => {
+            require(2, "mv <source> <destination>")?;
+            tree.move_entry(current, paths[0], paths[1])?;
+        }
+    }
+    Ok(current)
+}
+
+/// Part one: the total size of all directories of at most `limit` bytes.
+fn sum_small_dirs(tree: &FileSystem, limit: u64) -> u64 {
+    Query::parse(&format!("dirs where size <= {}", limit))
+        .expect("a size bound always parses")
+        .run(tree)
+        .iter()
+        .map(|entry| entry.size)
+        .sum()
+}
+
+struct Config {
+    pub filename: String,
+    pub tree: bool,
+    pub du: Option<SortBy>,
+    pub max_depth: Option<usize>,
+    pub to_json: bool,
+    pub from_json: bool,
+    pub transcript: bool,
+    pub from_disk: Option<String>,
+    pub synthetic: Option<usize>,
+    pub query: Option<String>,
+    pub replay: Option<String>,
+    pub disk_size: u64,
+    pub required: u64,
+    pub best_set: bool,
+    pub max_states: usize,
+}
+
+impl Config {
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
+        // iter past name
+        args.next();
+
+        let mut config = Config {
+            filename: "../input.txt".to_string(),
+            tree: false,
+            du: None,
+            max_depth: None,
+            to_json: false,
+            from_json: false,
+            transcript: false,
+            from_disk: None,
+            synthetic: None,
+            query: None,
+            replay: None,
+            disk_size: 70000000,
+            required: 30000000,
+            best_set: false,
+            max_states: 1000000,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tree" => config.tree = true,
+                "--json" => config.to_json = true,
+                "--from-json" => config.from_json = true,
+                "--transcript" => config.transcript = true,
+                "--best-set" => config.best_set = true,
+                "--max-states" => {
+                    let states = args.next().ok_or("Missing value for --max-states")?;
+                    config.max_states = states
+                        .parse()
+                        .map_err(|_| "Invalid value for --max-states")?;
+                }
+                "--from-disk" => {
+                    config.from_disk = Some(args.next().ok_or("Missing value for --from-disk")?);
+                }
+                "--query" => {
+                    config.query = Some(args.next().ok_or("Missing value for --query")?);
+                }
+                "--replay" => {
+                    config.replay = Some(args.next().ok_or("Missing value for --replay")?);
+                }
+                "--disk" => {
+                    let size = args.next().ok_or("Missing value for --disk")?;
+                    config.disk_size = size.parse().map_err(|_| "Invalid value for --disk")?;
+                }
+                "--required" => {
+                    let size = args.next().ok_or("Missing value for --required")?;
+                    config.required = size.parse().map_err(|_| "Invalid value for --required")?;
+                }
+                "--synthetic" => {
+                    let n_dirs = args.next().ok_or("Missing value for --synthetic")?;
+                    config.synthetic = Some(
+                        n_dirs
+                            .parse()
+                            .map_err(|_| "Invalid value for --synthetic")?,
+                    );
+                }
+                "--du" => config.du = config.du.or(Some(SortBy::Size)),
+                "--sort" => {
+                    config.du = match args.next().as_deref() {
+                        Some("size") => Some(SortBy::Size),
+                        Some("name") => Some(SortBy::Name),
+                        _ => return Err("--sort takes size or name"),
+                    }
+                }
+                "--max-depth" => {
+                    let depth = args.next().ok_or("Missing value for --max-depth")?;
+                    config.max_depth =
+                        Some(depth.parse().map_err(|_| "Invalid value for --max-depth")?);
+                }
+                _ if arg.starts_with("--") => return Err("Unknown option"),
+                _ => config.filename = arg,
+            }
         }
+        Ok(config)
     }
 }
 
 fn main() {
-    let contents = fs::read_to_string("../input.txt").expect("Missing input file");
-    let tree = build_tree(&contents);
+    let config = Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        process::exit(1);
+    });
+    let mut tree = if let Some(root) = &config.from_disk {
+        let (tree, skipped) = transcript::from_disk(Path::new(root)).unwrap_or_else(|err| {
+            eprintln!("Unable to walk {}: {}", root, err);
+            process::exit(1);
+        });
+        for path in skipped {
+            eprintln!(
+                "Skipping {}: its name cannot appear in a session",
+                path.display()
+            );
+        }
+        tree
+    } else if let Some(n_dirs) = config.synthetic {
+        transcript::synthetic_tree(n_dirs, 10, 7)
+    } else {
+        let contents = fs::read_to_string(&config.filename).expect("Missing input file");
+        if config.from_json {
+            json::from_json(&contents).unwrap_or_else(|err| {
+                eprintln!("Invalid filesystem JSON: {}", err);
+                process::exit(1);
+            })
+        } else {
+            build_tree(&contents).unwrap_or_else(|err| {
+                eprintln!("Invalid terminal session: {}", err);
+                process::exit(1);
+            })
+        }
+    };
+
+    if let Some(filename) = &config.replay {
+        let session = fs::read_to_string(filename).expect("Missing session file");
+        let before = tree.get_size(FileSystem::ROOT).0;
+        apply_session(&mut tree, &session).unwrap_or_else(|err| {
+            eprintln!("Invalid terminal session {}: {}", filename, err);
+            process::exit(1);
+        });
+        let planner = CleanupPlanner::new(config.disk_size, config.required);
+        println!(
+            "Replayed {}: freed {} bytes, {} more needed",
+            filename,
+            before.saturating_sub(tree.get_size(FileSystem::ROOT).0),
+            planner.bytes_to_free(&tree)
+        );
+    }
 
-    let mut sizes: Vec<(u64, String)> = vec![];
-    get_sizes(&tree, &mut sizes);
+    if config.transcript {
+        print!("{}", transcript::to_transcript(&tree));
+        return;
+    }
+    if config.to_json {
+        println!("{}", json::to_json(&tree));
+        return;
+    }
 
-    let totals: u64 = sizes.iter().filter(|s| s.0 <= 100000).map(|t| t.0).sum();
-    println!("Part 1: {}", totals); // 1084134
+    if let Some(text) = &config.query {
+        let query = Query::parse(text).unwrap_or_else(|err| {
+            eprintln!("Invalid query: {}", err);
+            process::exit(1);
+        });
+        for entry in query.run(&tree) {
+            println!("{}\t{}", entry.size, entry.path);
+        }
+        return;
+    }
+
+    if config.tree || config.du.is_some() {
+        if config.tree {
+            println!("{}", report::render_tree(&tree, config.max_depth));
+        }
+        if let Some(sort_by) = config.du {
+            for (size, path) in report::du(&tree, sort_by, config.max_depth) {
+                println!("{}\t{}", size, path);
+            }
+        }
+        return;
+    }
+
+    let unexplored = tree.unexplored_dirs();
+    if !unexplored.is_empty() {
+        println!(
+            "Warning: {} directories were never listed, sizes may be incomplete:",
+            unexplored.len()
+        );
+        for id in unexplored {
+            println!("  {}", tree.path(id));
+        }
+    }
 
-    let (outer_size, _) = tree.borrow_mut().get_size();
-    let total_free: u64 = 70000000 - outer_size;
-    let total_needed = 30000000 - total_free;
+    let totals = sum_small_dirs(&tree, 100000);
+    println!("Part 1: {}", totals); // 1084134
 
-    let mut sizes: Vec<(u64, String)> = vec![];
-    get_sizes(&tree, &mut sizes);
-    sizes.sort_by(|a, b| a.0.cmp(&b.0));
+    let planner = CleanupPlanner::new(config.disk_size, config.required);
+    if planner.bytes_to_free(&tree) == 0 {
+        println!("Part 2: enough space is free, nothing needs deleting");
+        return;
+    }
+    match planner.smallest_dir(&tree) {
+        Some(id) => println!("Part 2: {}", tree.get_size(id).0),
+        None => println!("Part 2: no directory frees enough space"),
+    }
 
-    let mut filtered: Vec<_> = sizes.iter().filter(|s| s.0 >= total_needed).collect();
-    filtered.sort_by(|a, b| a.0.cmp(&b.0));
-    println!("Part 2: {:?}", filtered.first().unwrap().0);
+    if config.best_set {
+        match planner.best_set(&tree, config.max_states) {
+            Ok(Some(best)) => {
+                let freed: u64 = best.iter().map(|&id| tree.get_size(id).0).sum();
+                let mut paths: Vec<String> = best.iter().map(|&id| tree.path(id)).collect();
+                paths.sort();
+                println!(
+                    "Best cleanup: delete {} ({} bytes)",
+                    paths.join(", "),
+                    freed
+                );
+            }
+            Ok(None) => println!("Best cleanup: no set of directories frees enough space"),
+            Err(err) => {
+                eprintln!("Unable to plan a cleanup: {}", err);
+                process::exit(1);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -192,33 +828,33 @@ mod tests {
         assert_eq!(
             Line::Command(Command {
                 kind: CommandKind::ChangeDir,
-                argument: Some("/".to_string())
+                arguments: vec!["/".to_string()]
             }),
-            parse_line("$ cd /")
+            parse_line("$ cd /").unwrap()
         );
 
         assert_eq!(
             Line::Command(Command {
                 kind: CommandKind::ChangeDir,
-                argument: Some("..".to_string())
+                arguments: vec!["..".to_string()]
             }),
-            parse_line("$ cd ..")
+            parse_line("$ cd ..").unwrap()
         );
 
         assert_eq!(
             Line::Command(Command {
                 kind: CommandKind::ChangeDir,
-                argument: Some("f".to_string())
+                arguments: vec!["f".to_string()]
             }),
-            parse_line("$ cd f")
+            parse_line("$ cd f").unwrap()
         );
 
         assert_eq!(
             Line::Command(Command {
                 kind: CommandKind::ListDir,
-                argument: None
+                arguments: vec![]
             }),
-            parse_line("$ ls")
+            parse_line("$ ls").unwrap()
         );
 
         assert_eq!(
@@ -226,7 +862,7 @@ mod tests {
                 name: "d".to_string(),
                 contains: vec![]
             })),
-            parse_line("dir d")
+            parse_line("dir d").unwrap()
         );
 
         assert_eq!(
@@ -234,7 +870,7 @@ mod tests {
                 name: "f".to_string(),
                 size: 29116
             })),
-            parse_line("29116 f")
+            parse_line("29116 f").unwrap()
         );
 
         assert_eq!(
@@ -242,7 +878,265 @@ mod tests {
                 name: "f".to_string(),
                 size: 0
             })),
-            parse_line("0 f")
-        )
+            parse_line("0 f").unwrap()
+        );
+
+        assert_eq!(
+            Line::Command(Command {
+                kind: CommandKind::Move,
+                arguments: vec!["a".to_string(), "/b".to_string()]
+            }),
+            parse_line("$ mv a /b").unwrap()
+        );
+        assert_eq!(Line::Empty, parse_line("").unwrap());
+        assert_eq!(
+            Line::Command(Command {
+                kind: CommandKind::Unk,
+                arguments: vec![]
+            }),
+            parse_line("$").unwrap()
+        );
+        assert_eq!(Line::Empty, parse_line("  ").unwrap());
+
+        for line in [
+            "123",
+            "dir",
+            "dir a b",
+            "18446744073709551616 f",
+            "12 ..",
+            "abc d",
+            "x",
+        ] {
+            assert_eq!(
+                Err(SessionErrorKind::InvalidLine(line.to_string())),
+                parse_line(line)
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let contents = include_str!("../../sample_input.txt");
+        let tree = build_tree(contents).unwrap();
+
+        assert_eq!(
+            vec![
+                (48381165, "/".to_string()),
+                (24933642, "/d".to_string()),
+                (94853, "/a".to_string()),
+                (584, "/a/e".to_string())
+            ],
+            report::du(&tree, SortBy::Size, None)
+        );
+        assert_eq!(95437, sum_small_dirs(&tree, 100000));
+        assert!(tree.unexplored_dirs().is_empty());
+        let smallest = CleanupPlanner::new(70000000, 30000000).smallest_dir(&tree);
+        assert_eq!(Some(24933642), smallest.map(|id| tree.get_size(id).0));
+    }
+
+    #[test]
+    fn test_build_tree_revisits() {
+        // revisiting directories, relisting them and jumping back to / must
+        // not duplicate anything
+        let contents = include_str!("../../sample_input.txt").to_string()
+            + "\n$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d\n\
+               $ cd a\n$ cd e\n$ ls\n584 i\n$ cd /a/e\n$ cd ../../d\n$ ls\n4060174 j\n";
+        let tree = build_tree(&contents).unwrap();
+        assert_eq!(
+            vec![
+                (48381165, "/".to_string()),
+                (24933642, "/d".to_string()),
+                (94853, "/a".to_string()),
+                (584, "/a/e".to_string())
+            ],
+            report::du(&tree, SortBy::Size, None)
+        );
+    }
+
+    #[test]
+    fn test_build_tree_errors() {
+        let err = build_tree("$ cd /\n$ ls\n123\n").unwrap_err();
+        assert_eq!(
+            "line 3: \"123\" is neither a command nor a listing entry",
+            err.to_string()
+        );
+        assert_eq!(
+            Err(SessionError {
+                line: 2,
+                kind: SessionErrorKind::AboveRoot
+            }),
+            build_tree("$ cd /\n$ cd ..\n")
+        );
+        assert_eq!(
+            Err(SessionError {
+                line: 5,
+                kind: SessionErrorKind::ConflictingSize {
+                    name: "f".to_string(),
+                    listed: 10,
+                    relisted: 11
+                }
+            }),
+            build_tree("$ cd /\n$ ls\n10 f\n$ ls\n11 f\n")
+        );
+        assert_eq!(
+            Err(SessionError {
+                line: 4,
+                kind: SessionErrorKind::NotADirectory("f".to_string())
+            }),
+            build_tree("$ cd /\n$ ls\n10 f\n$ cd f\n")
+        );
+        assert_eq!(
+            Err(SessionError {
+                line: 4,
+                kind: SessionErrorKind::NotAFile("a".to_string())
+            }),
+            build_tree("$ cd a\n$ cd ..\n$ ls\n10 a\n")
+        );
+        let err = build_tree("$ cd\n").unwrap_err();
+        assert_eq!("line 1: usage: cd <path>", err.to_string());
+        assert_eq!(
+            SessionErrorKind::UnknownCommand,
+            build_tree("$ pwd\n").unwrap_err().kind
+        );
+    }
+
+    #[test]
+    fn test_shell_commands() {
+        let mut tree = build_tree(include_str!("../../sample_input.txt")).unwrap();
+        let session = "\
+$ cd /a
+$ rm -r e
+$ rm ../b.txt
+$ mv h.lst /d/h.lst
+$ mv /d/j g
+$ mkdir -p x/y
+$ touch x/y/z
+$ cd x/y
+$ mv /a/x /d
+$ rm -f nothing
+";
+        apply_session(&mut tree, session).unwrap();
+        let expected = "\
+- / (dir, size=33529510)
+  - a (dir, size=4089290)
+    - f (file, size=29116)
+    - g (file, size=4060174)
+  - c.dat (file, size=8504156)
+  - d (dir, size=20936064)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - h.lst (file, size=62596)
+    - k (file, size=7214296)
+    - x (dir, size=0)
+      - y (dir, size=0)
+        - z (file, size=0)";
+        assert_eq!(expected, report::render_tree(&tree, None));
+        assert!(tree.unexplored_dirs().is_empty());
+
+        // deleting the planned directories frees what the planner promised
+        let planner = CleanupPlanner::new(40000000, 10000000);
+        let best = planner.best_set(&tree, 1_000_000).unwrap().unwrap();
+        let freed: u64 = best.iter().map(|&id| tree.get_size(id).0).sum();
+        let script: String = best
+            .iter()
+            .map(|&id| format!("$ rm -r {}\n", tree.path(id)))
+            .collect();
+        let before = tree.get_size(FileSystem::ROOT).0;
+        apply_session(&mut tree, &script).unwrap();
+        assert_eq!(before - freed, tree.get_size(FileSystem::ROOT).0);
+        assert_eq!(0, planner.bytes_to_free(&tree));
+    }
+
+    #[test]
+    fn test_remove_current_dir() {
+        // removing a directory the session is inside moves it up to the
+        // parent of the removed directory
+        let contents = include_str!("../../sample_input.txt").to_string()
+            + "\n$ cd /a/e\n$ rm -r /a\n$ ls\n5 x\n$ mkdir y\n";
+        let tree = build_tree(&contents).unwrap();
+        let expected = "\
+- / (dir, size=48286317)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+  - x (file, size=5)
+  - y (dir, size=0)";
+        assert_eq!(expected, report::render_tree(&tree, None));
+
+        let contents = include_str!("../../sample_input.txt").to_string()
+            + "\n$ cd /a/e\n$ rm -r ../e\n$ ls\n5 x\n";
+        let tree = build_tree(&contents).unwrap();
+        let a = tree.child(FileSystem::ROOT, "a").unwrap();
+        assert!(tree.child(a, "e").is_none());
+        assert!(tree.file(a, "x").is_some());
+    }
+
+    #[test]
+    fn test_shell_command_errors() {
+        let tree = include_str!("../../sample_input.txt");
+        let kind = |session: &str| {
+            let mut tree = build_tree(tree).unwrap();
+            apply_session(&mut tree, session).unwrap_err().kind
+        };
+        assert_eq!(SessionErrorKind::NotAFile("d".to_string()), kind("$ rm d"));
+        assert_eq!(
+            SessionErrorKind::NoSuchEntry("/a/x".to_string()),
+            kind("$ rm /a/x")
+        );
+        assert_eq!(
+            SessionErrorKind::InvalidTarget("/".to_string()),
+            kind("$ rm -r /")
+        );
+        assert_eq!(
+            SessionErrorKind::AlreadyExists("a".to_string()),
+            kind("$ mkdir a")
+        );
+        assert_eq!(
+            SessionErrorKind::NoSuchEntry("x".to_string()),
+            kind("$ mkdir x/y")
+        );
+        assert_eq!(
+            SessionErrorKind::MoveIntoItself("/a".to_string()),
+            kind("$ mv /a /a/e")
+        );
+        assert_eq!(
+            SessionErrorKind::NotADirectory("b.txt".to_string()),
+            kind("$ mv a b.txt")
+        );
+        assert_eq!(
+            SessionErrorKind::AlreadyExists("/a".to_string()),
+            kind("$ mv /a/e /a")
+        );
+        let err = build_tree("$ cd /\n$ mv a\n").unwrap_err();
+        assert_eq!("line 2: usage: mv <source> <destination>", err.to_string());
+    }
+
+    #[test]
+    fn test_unexplored_dirs() {
+        let contents = "$ cd /\n$ ls\ndir a\ndir b\n10 f\n$ cd a\n$ ls\ndir c\n20 g\n$ cd /b/x\n";
+        let tree = build_tree(contents).unwrap();
+
+        let paths: Vec<String> = tree.pre_order().iter().map(|&id| tree.path(id)).collect();
+        assert_eq!(vec!["/", "/a", "/a/c", "/b", "/b/x"], paths);
+
+        let unexplored: Vec<String> = tree
+            .unexplored_dirs()
+            .iter()
+            .map(|&id| tree.path(id))
+            .collect();
+        assert_eq!(vec!["/a/c", "/b", "/b/x"], unexplored);
+        assert_eq!(30, tree.get_size(FileSystem::ROOT).0);
+
+        assert_eq!(
+            Err(SessionError {
+                line: 4,
+                kind: SessionErrorKind::NotADirectory("f".to_string())
+            }),
+            build_tree("$ ls\n10 f\n$ ls\ndir f\n")
+        );
     }
 }
@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::{FileSystem, NodeId};
+
+/// Plans which directories to delete so that an update fits on the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupPlanner {
+    pub disk_size: u64,
+    pub required: u64,
+}
+
+impl CleanupPlanner {
+    pub fn new(disk_size: u64, required: u64) -> CleanupPlanner {
+        CleanupPlanner {
+            disk_size,
+            required,
+        }
+    }
+
+    /// Bytes which must be deleted before `required` bytes are free.
+    pub fn bytes_to_free(&self, tree: &FileSystem) -> u64 {
+        let (used, _) = tree.get_size(FileSystem::ROOT);
+        let free = self.disk_size.saturating_sub(used);
+        self.required.saturating_sub(free)
+    }
+
+    /// The smallest directory which frees enough space on its own.
+    pub fn smallest_dir(&self, tree: &FileSystem) -> Option<NodeId> {
+        let to_free = self.bytes_to_free(tree);
+        tree.pre_order()
+            .into_iter()
+            .filter(|&id| tree.get_size(id).0 >= to_free)
+            .min_by_key(|&id| tree.get_size(id).0)
+    }
+
+    /// The set of directories, none inside another, which frees enough space
+    /// while deleting as few bytes as possible, or `None` if even deleting
+    /// everything is not enough.
+    ///
+    /// This is a branch-and-bound search over the directories in pre-order,
+    /// where deleting a directory skips its whole subtree. The smallest
+    /// sufficient single directory is the starting bound, and a branch is
+    /// cut when it cannot beat the bound or can no longer free enough. The
+    /// work depends on the number of directories rather than their sizes,
+    /// but can still grow quickly, so the search gives up after visiting
+    /// `max_states` distinct states.
+    pub fn best_set(
+        &self,
+        tree: &FileSystem,
+        max_states: usize,
+    ) -> Result<Option<Vec<NodeId>>, PlanError> {
+        let to_free = self.bytes_to_free(tree);
+        if to_free == 0 {
+            return Ok(Some(vec![]));
+        }
+        let Some(smallest) = self.smallest_dir(tree) else {
+            return Ok(None);
+        };
+
+        let order = tree.pre_order();
+        let n = order.len();
+        let sizes: Vec<u64> = order.iter().map(|&id| tree.get_size(id).0).collect();
+        let mut position = vec![0; tree.nodes.len()];
+        for (idx, &id) in order.iter().enumerate() {
+            position[id] = idx;
+        }
+        // the position just past the subtree of each directory
+        let mut end: Vec<usize> = (1..=n).collect();
+        for idx in (0..n).rev() {
+            if let Some(&last) = tree.nodes[order[idx]].children.last() {
+                end[idx] = end[position[last]];
+            }
+        }
+        // the most that can still be freed from each position on, by
+        // deleting every remaining top-level directory
+        let mut reachable = vec![0; n + 1];
+        for idx in (0..n).rev() {
+            reachable[idx] = sizes[idx] + reachable[end[idx]];
+        }
+
+        let mut search = Search {
+            sizes: &sizes,
+            end: &end,
+            reachable: &reachable,
+            to_free,
+            best: (sizes[position[smallest]], vec![position[smallest]]),
+            seen: HashSet::new(),
+            max_states,
+        };
+        search.run()?;
+        let (_, best) = search.best;
+        Ok(Some(best.into_iter().map(|idx| order[idx]).collect()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanError {
+    LimitReached(usize),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::LimitReached(limit) => {
+                write!(f, "gave up after exploring {} states", limit)
+            }
+        }
+    }
+}
+
+impl Error for PlanError {}
+
+// State of the branch-and-bound search, with directories referred to by
+// their pre-order position.
+struct Search<'a> {
+    sizes: &'a [u64],
+    end: &'a [usize],
+    reachable: &'a [u64],
+    to_free: u64,
+    /// The smallest sufficient total found so far and its directories.
+    best: (u64, Vec<usize>),
+    seen: HashSet<(usize, u64)>,
+    max_states: usize,
+}
+
+impl Search<'_> {
+    fn run(&mut self) -> Result<(), PlanError> {
+        // Skipping a directory moves along the pre-order in the inner loop;
+        // deleting one pushes the position past its subtree, together with
+        // how many directories are chosen before it and the directory
+        // itself. Branches are taken last in, first out, so `chosen` only
+        // ever needs truncating back to the branch point.
+        let mut chosen: Vec<usize> = vec![];
+        let mut pending = vec![(0, 0, 0, None)];
+        while let Some((mut idx, freed, depth, taken)) = pending.pop() {
+            chosen.truncate(depth);
+            chosen.extend(taken);
+            while idx < self.sizes.len()
+                && freed + self.reachable[idx] >= self.to_free
+                && self.best.0 > self.to_free
+            {
+                // reaching the same position with the same total again
+                // cannot lead anywhere new
+                if !self.seen.insert((idx, freed)) {
+                    break;
+                }
+                if self.seen.len() > self.max_states {
+                    return Err(PlanError::LimitReached(self.max_states));
+                }
+
+                let total = freed + self.sizes[idx];
+                if total < self.best.0 {
+                    if total >= self.to_free {
+                        let mut best = chosen.clone();
+                        best.push(idx);
+                        self.best = (total, best);
+                    } else {
+                        pending.push((self.end[idx], total, chosen.len(), Some(idx)));
+                    }
+                }
+                idx += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tree;
+    use crate::json::from_json;
+
+    fn paths(tree: &FileSystem, ids: &[NodeId]) -> Vec<String> {
+        let mut paths: Vec<String> = ids.iter().map(|&id| tree.path(id)).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_sample() {
+        let tree = build_tree(include_str!("../../sample_input.txt")).unwrap();
+        let planner = CleanupPlanner::new(70000000, 30000000);
+        assert_eq!(8381165, planner.bytes_to_free(&tree));
+
+        let smallest = planner.smallest_dir(&tree).unwrap();
+        assert_eq!("/d", tree.path(smallest));
+
+        let best = planner.best_set(&tree, 1_000_000).unwrap().unwrap();
+        assert_eq!(vec!["/d"], paths(&tree, &best));
+
+        assert_eq!(
+            Ok(Some(vec![])),
+            CleanupPlanner::new(70000000, 100).best_set(&tree, 1_000_000)
+        );
+        assert_eq!(
+            Ok(None),
+            CleanupPlanner::new(100, 48381166).best_set(&tree, 1_000_000)
+        );
+    }
+
+    #[test]
+    fn test_set_beats_single_dir() {
+        // freeing 100 bytes: a (60) + b/c (45) = 105 beats the smallest
+        // single directory b (150)
+        let json = r#"{
+            "name": "/",
+            "files": [{ "name": "big", "size": 1000 }],
+            "dirs": [
+                { "name": "a", "files": [{ "name": "f", "size": 60 }] },
+                { "name": "b", "files": [{ "name": "f", "size": 60 }], "dirs": [
+                    { "name": "c", "files": [{ "name": "f", "size": 45 }] },
+                    { "name": "d", "files": [{ "name": "f", "size": 45 }] }
+                ] }
+            ]
+        }"#;
+        let tree = from_json(json).unwrap();
+        let planner = CleanupPlanner::new(1210, 100);
+        assert_eq!(100, planner.bytes_to_free(&tree));
+        assert_eq!("/b", tree.path(planner.smallest_dir(&tree).unwrap()));
+
+        let best = planner.best_set(&tree, 1_000_000).unwrap().unwrap();
+        let paths = paths(&tree, &best);
+        assert!(paths == vec!["/a", "/b/c"] || paths == vec!["/a", "/b/d"]);
+        let freed: u64 = best.iter().map(|&id| tree.get_size(id).0).sum();
+        assert_eq!(105, freed);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let tree = crate::transcript::synthetic_tree(12, 3, 5);
+        let dirs: Vec<NodeId> = tree.pre_order();
+        let (used, _) = tree.get_size(FileSystem::ROOT);
+        for required in [used / 10, used / 4, used / 2, used - 1] {
+            let planner = CleanupPlanner::new(used, required);
+            let to_free = planner.bytes_to_free(&tree);
+
+            // every subset of directories in which none contains another
+            let nested = |a: NodeId, b: NodeId| {
+                tree.path(b).starts_with(&format!("{}/", tree.path(a))) || a == FileSystem::ROOT
+            };
+            let mut expected = None;
+            for mask in 1u32..(1 << dirs.len()) {
+                let set: Vec<NodeId> = (0..dirs.len())
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| dirs[i])
+                    .collect();
+                let valid = set
+                    .iter()
+                    .all(|&a| set.iter().all(|&b| a == b || !nested(a, b)));
+                let total: u64 = set.iter().map(|&id| tree.get_size(id).0).sum();
+                if valid && total >= to_free {
+                    expected = Some(expected.map_or(total, |e: u64| e.min(total)));
+                }
+            }
+
+            let best = planner.best_set(&tree, 1_000_000).unwrap().unwrap();
+            let total: u64 = best.iter().map(|&id| tree.get_size(id).0).sum();
+            assert_eq!(expected, Some(total));
+        }
+    }
+
+    #[test]
+    fn test_state_limit() {
+        let tree = crate::transcript::synthetic_tree(2000, 10, 7);
+        let (used, _) = tree.get_size(FileSystem::ROOT);
+        let planner = CleanupPlanner::new(used, used / 3);
+        assert_eq!(
+            Err(PlanError::LimitReached(10)),
+            planner.best_set(&tree, 10)
+        );
+
+        // running out of states on a large tree must not run out of stack
+        let tree = crate::transcript::synthetic_tree(100000, 1, 7);
+        let (used, _) = tree.get_size(FileSystem::ROOT);
+        let planner = CleanupPlanner::new(used, used / 3);
+        assert_eq!(
+            Err(PlanError::LimitReached(200000)),
+            planner.best_set(&tree, 200000)
+        );
+        assert_eq!(
+            "gave up after exploring 10 states",
+            PlanError::LimitReached(10).to_string()
+        );
+    }
+}
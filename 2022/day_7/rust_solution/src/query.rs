@@ -0,0 +1,412 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::FileSystem;
+
+/// What an entry of the tree is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+}
+
+/// A file or directory matched by a query. Directories carry their
+/// cumulative size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: Kind,
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Less,
+    AtMost,
+    Equal,
+    NotEqual,
+    AtLeast,
+    Greater,
+}
+
+impl Op {
+    fn parse(word: &str) -> Option<Op> {
+        match word {
+            "<" => Some(Op::Less),
+            "<=" => Some(Op::AtMost),
+            "=" | "==" => Some(Op::Equal),
+            "!=" => Some(Op::NotEqual),
+            ">=" => Some(Op::AtLeast),
+            ">" => Some(Op::Greater),
+            _ => None,
+        }
+    }
+
+    fn holds(self, value: u64, bound: u64) -> bool {
+        match self {
+            Op::Less => value < bound,
+            Op::AtMost => value <= bound,
+            Op::Equal => value == bound,
+            Op::NotEqual => value != bound,
+            Op::AtLeast => value >= bound,
+            Op::Greater => value > bound,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    Size(Op, u64),
+    /// Glob over the last path component; `matches` is false for `!=`.
+    Name {
+        glob: String,
+        matches: bool,
+    },
+    /// Glob over the full path, where `**` spans any number of directories.
+    Path {
+        glob: String,
+        matches: bool,
+    },
+}
+
+impl Condition {
+    fn holds(&self, entry: &Entry) -> bool {
+        match self {
+            Condition::Size(op, bound) => op.holds(entry.size, *bound),
+            Condition::Name { glob, matches } => {
+                let name = entry.path.rsplit('/').next().unwrap();
+                glob_match(glob, name) == *matches
+            }
+            Condition::Path { glob, matches } => path_match(glob, &entry.path) == *matches,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    Empty,
+    /// A word which does not fit where it appears.
+    Unexpected(String),
+    /// An option or comparison which is missing its value.
+    MissingValue(String),
+    InvalidSize(String),
+    InvalidType(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::Empty => write!(f, "empty query"),
+            QueryError::Unexpected(word) => write!(f, "unexpected {}", word),
+            QueryError::MissingValue(word) => write!(f, "{} needs a value", word),
+            QueryError::InvalidSize(word) => write!(f, "{} is not a size", word),
+            QueryError::InvalidType(word) => write!(f, "{} is not f or d", word),
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+/// A filter over the files and directories of a tree, written either like
+/// `find`:
+///
+/// ```text
+/// find /a/** -type f -size +100000 -name *.txt
+/// ```
+///
+/// or as a condition over one kind of entry:
+///
+/// ```text
+/// dirs where size <= 100000 and path != /d/**
+/// ```
+///
+/// A `find` path without wildcards matches the directory and everything
+/// below it. `-size +N` and `-size -N` mean more and less than `N` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    kind: Option<Kind>,
+    conditions: Vec<Condition>,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let mut words = text.split_whitespace();
+        match words.next() {
+            None => Err(QueryError::Empty),
+            Some("find") => Query::parse_find(words),
+            Some(kind) => Query::parse_where(kind, words),
+        }
+    }
+
+    fn parse_find<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<Query, QueryError> {
+        let mut query = Query {
+            kind: None,
+            conditions: vec![],
+        };
+        while let Some(word) = words.next() {
+            let mut value = || {
+                words
+                    .next()
+                    .ok_or(QueryError::MissingValue(word.to_string()))
+            };
+            match word {
+                "-type" => {
+                    query.kind = match value()? {
+                        "f" => Some(Kind::File),
+                        "d" => Some(Kind::Dir),
+                        other => return Err(QueryError::InvalidType(other.to_string())),
+                    }
+                }
+                "-size" => {
+                    let size = value()?;
+                    let (op, bound) = match size.as_bytes().first() {
+                        Some(b'+') => (Op::Greater, &size[1..]),
+                        Some(b'-') => (Op::Less, &size[1..]),
+                        _ => (Op::Equal, size),
+                    };
+                    query
+                        .conditions
+                        .push(Condition::Size(op, parse_size(bound)?));
+                }
+                "-name" => query.conditions.push(Condition::Name {
+                    glob: value()?.to_string(),
+                    matches: true,
+                }),
+                _ if word.starts_with('-') => return Err(QueryError::Unexpected(word.to_string())),
+                _ => {
+                    let glob = if word.contains(['*', '?']) {
+                        word.to_string()
+                    } else {
+                        format!("{}/**", word.trim_end_matches('/'))
+                    };
+                    query.conditions.push(Condition::Path {
+                        glob,
+                        matches: true,
+                    });
+                }
+            }
+        }
+        Ok(query)
+    }
+
+    fn parse_where<'a>(
+        kind: &str,
+        mut words: impl Iterator<Item = &'a str>,
+    ) -> Result<Query, QueryError> {
+        let kind = match kind {
+            "dirs" => Some(Kind::Dir),
+            "files" => Some(Kind::File),
+            "entries" => None,
+            _ => return Err(QueryError::Unexpected(kind.to_string())),
+        };
+        let mut query = Query {
+            kind,
+            conditions: vec![],
+        };
+        let mut joiner = "where";
+        while let Some(word) = words.next() {
+            if word != joiner {
+                return Err(QueryError::Unexpected(word.to_string()));
+            }
+            joiner = "and";
+            let field = words
+                .next()
+                .ok_or(QueryError::MissingValue(word.to_string()))?;
+            let op = words
+                .next()
+                .ok_or(QueryError::MissingValue(field.to_string()))?;
+            let value = words
+                .next()
+                .ok_or(QueryError::MissingValue(op.to_string()))?;
+            let unexpected = QueryError::Unexpected(op.to_string());
+            let op = Op::parse(op).ok_or(unexpected.clone())?;
+            let matches = match op {
+                Op::Equal => true,
+                Op::NotEqual => false,
+                _ if field != "size" => return Err(unexpected),
+                _ => true,
+            };
+            let glob = value.to_string();
+            query.conditions.push(match field {
+                "size" => Condition::Size(op, parse_size(value)?),
+                "name" => Condition::Name { glob, matches },
+                "path" => Condition::Path { glob, matches },
+                _ => return Err(QueryError::Unexpected(field.to_string())),
+            });
+        }
+        Ok(query)
+    }
+
+    /// Every matching entry, each directory followed by its files and then
+    /// its subdirectories.
+    pub fn run(&self, tree: &FileSystem) -> Vec<Entry> {
+        let mut entries = vec![];
+        for id in tree.pre_order() {
+            let path = tree.path(id);
+            entries.push(Entry {
+                kind: Kind::Dir,
+                size: tree.get_size(id).0,
+                path: path.clone(),
+            });
+            let prefix = path.trim_end_matches('/');
+            entries.extend(tree.nodes[id].files.iter().map(|f| Entry {
+                kind: Kind::File,
+                path: format!("{}/{}", prefix, f.name),
+                size: f.size,
+            }));
+        }
+        entries.retain(|entry| self.matches(entry));
+        entries
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        self.kind.is_none_or(|kind| kind == entry.kind)
+            && self.conditions.iter().all(|c| c.holds(entry))
+    }
+}
+
+fn parse_size(word: &str) -> Result<u64, QueryError> {
+    word.parse()
+        .map_err(|_| QueryError::InvalidSize(word.to_string()))
+}
+
+/// Match one path component against a glob with `*` and `?`.
+fn glob_match(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // position of the last `*` and of the text it was tried against
+    let mut star = None;
+    let (mut g, mut t) = (0, 0);
+    while t < text.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == text[t]) {
+            g += 1;
+            t += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, t));
+            g += 1;
+        } else if let Some((star_g, star_t)) = star {
+            // let the last `*` swallow one more character
+            star = Some((star_g, star_t + 1));
+            g = star_g + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+/// Match a full path against a glob, component by component.
+fn path_match(glob: &str, path: &str) -> bool {
+    let glob: Vec<&str> = glob.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match_components(&glob, &path)
+}
+
+fn match_components(glob: &[&str], path: &[&str]) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_components(rest, &path[skip..])),
+        Some((first, rest)) => {
+            !path.is_empty() && glob_match(first, path[0]) && match_components(rest, &path[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tree;
+
+    fn paths(query: &str) -> Vec<String> {
+        let tree = build_tree(include_str!("../../sample_input.txt")).unwrap();
+        Query::parse(query)
+            .unwrap()
+            .run(&tree)
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.txt", "b.txt"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("d.*", "d.ext"));
+        assert!(glob_match("?.l?g", "d.log"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("*.txt", "b.txt.bak"));
+        assert!(!glob_match("?", "ab"));
+
+        assert!(path_match("/a/**", "/a"));
+        assert!(path_match("/a/**", "/a/e/i"));
+        assert!(path_match("/**/i", "/a/e/i"));
+        assert!(path_match("/*/e", "/a/e"));
+        assert!(path_match("/**", "/"));
+        assert!(!path_match("/*/i", "/a/e/i"));
+        assert!(!path_match("/a/**", "/d"));
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(vec!["/a/e/i"], paths("find /a/** -type f -size -1000"));
+        assert_eq!(
+            vec!["/a/f", "/a/g", "/a/h.lst", "/a/e/i"],
+            paths("find /a -type f")
+        );
+        assert_eq!(vec!["/", "/a", "/a/e", "/d"], paths("find / -type d"));
+        assert_eq!(
+            vec!["/b.txt", "/c.dat", "/d/d.log", "/d/d.ext", "/d/k"],
+            paths("find -type f -size +5000000")
+        );
+        assert_eq!(vec!["/d/d.log", "/d/d.ext"], paths("find /d/* -name d.*"));
+        assert_eq!(vec!["/d/j"], paths("find -size 4060174"));
+    }
+
+    #[test]
+    fn test_where() {
+        assert_eq!(vec!["/a", "/a/e"], paths("dirs where size <= 100000"));
+        assert_eq!(
+            vec!["/a/h.lst"],
+            paths("files where name = *.lst and size > 1000")
+        );
+        assert_eq!(
+            vec!["/", "/a", "/a/e/i"],
+            paths("entries where path != /d/** and path != /a/* and name != *.*")
+        );
+        assert_eq!(4, paths("dirs").len());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Err(QueryError::Empty), Query::parse("  "));
+        assert_eq!(
+            Err(QueryError::InvalidType("x".to_string())),
+            Query::parse("find / -type x")
+        );
+        assert_eq!(
+            Err(QueryError::MissingValue("-size".to_string())),
+            Query::parse("find / -size")
+        );
+        assert_eq!(
+            Err(QueryError::InvalidSize("+big".to_string())),
+            Query::parse("find / -size ++big")
+        );
+        assert_eq!(
+            Err(QueryError::Unexpected("size".to_string())),
+            Query::parse("dirs size < 3")
+        );
+        assert_eq!(
+            Err(QueryError::Unexpected("colour".to_string())),
+            Query::parse("dirs where colour = red")
+        );
+        assert_eq!(
+            Err(QueryError::Unexpected("<=".to_string())),
+            Query::parse("files where name <= a")
+        );
+        assert_eq!(
+            Err(QueryError::MissingValue("<".to_string())),
+            Query::parse("dirs where size <")
+        );
+    }
+}
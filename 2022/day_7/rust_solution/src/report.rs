@@ -0,0 +1,159 @@
+use crate::{FileSystem, NodeId};
+
+/// Order of the entries in a `du` listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Largest directories first, ties broken by path.
+    Size,
+    /// Alphabetical by full path.
+    Name,
+}
+
+impl FileSystem {
+    /// Number of directories between `id` and the root, which has depth 0.
+    pub fn depth(&self, id: NodeId) -> usize {
+        let mut depth = 0;
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            depth += 1;
+            current = parent;
+        }
+        depth
+    }
+}
+
+/// Render the tree the way the puzzle describes it, one entry per line with
+/// directories and files sorted by name:
+///
+/// ```text
+/// - / (dir, size=48381165)
+///   - a (dir, size=94853)
+///     - f (file, size=29116)
+/// ```
+///
+/// Entries deeper than `max_depth` are left out, the root being at depth 0.
+pub fn render_tree(tree: &FileSystem, max_depth: Option<usize>) -> String {
+    let mut lines = vec![];
+    render_dir(tree, FileSystem::ROOT, 0, max_depth, &mut lines);
+    lines.join("\n")
+}
+
+fn render_dir(
+    tree: &FileSystem,
+    id: NodeId,
+    depth: usize,
+    max_depth: Option<usize>,
+    lines: &mut Vec<String>,
+) {
+    let node = &tree.nodes[id];
+    let indent = "  ".repeat(depth);
+    let unexplored = if node.listed { "" } else { ", unexplored" };
+    lines.push(format!(
+        "{}- {} (dir, size={}{})",
+        indent,
+        node.name,
+        tree.get_size(id).0,
+        unexplored
+    ));
+    if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+        return;
+    }
+
+    // directories and files share one alphabetical listing
+    let mut entries: Vec<(&str, Option<NodeId>, u64)> = node
+        .children
+        .iter()
+        .map(|&child| (tree.nodes[child].name.as_str(), Some(child), 0))
+        .chain(node.files.iter().map(|f| (f.name.as_str(), None, f.size)))
+        .collect();
+    entries.sort();
+    for (name, child, size) in entries {
+        match child {
+            Some(child) => render_dir(tree, child, depth + 1, max_depth, lines),
+            None => lines.push(format!("{}  - {} (file, size={})", indent, name, size)),
+        }
+    }
+}
+
+/// Cumulative size and full path of every directory no deeper than
+/// `max_depth`, like `du`.
+pub fn du(tree: &FileSystem, sort_by: SortBy, max_depth: Option<usize>) -> Vec<(u64, String)> {
+    let mut entries: Vec<(u64, String)> = tree
+        .pre_order()
+        .into_iter()
+        .filter(|&id| max_depth.is_none_or(|max_depth| tree.depth(id) <= max_depth))
+        .map(|id| (tree.get_size(id).0, tree.path(id)))
+        .collect();
+    match sort_by {
+        SortBy::Size => entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1))),
+        SortBy::Name => entries.sort_by(|a, b| a.1.cmp(&b.1)),
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tree;
+
+    #[test]
+    fn test_render_tree() {
+        let tree = build_tree(include_str!("../../sample_input.txt")).unwrap();
+        let expected = "\
+- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)";
+        assert_eq!(expected, render_tree(&tree, None));
+
+        let expected = "\
+- / (dir, size=48381165)
+  - a (dir, size=94853)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)";
+        assert_eq!(expected, render_tree(&tree, Some(1)));
+
+        let tree = build_tree("$ ls\ndir x\n").unwrap();
+        assert_eq!(
+            "- / (dir, size=0)\n  - x (dir, size=0, unexplored)",
+            render_tree(&tree, None)
+        );
+    }
+
+    #[test]
+    fn test_du() {
+        let tree = build_tree(include_str!("../../sample_input.txt")).unwrap();
+        assert_eq!(
+            vec![
+                (48381165, "/".to_string()),
+                (24933642, "/d".to_string()),
+                (94853, "/a".to_string()),
+                (584, "/a/e".to_string()),
+            ],
+            du(&tree, SortBy::Size, None)
+        );
+        assert_eq!(
+            vec![
+                (48381165, "/".to_string()),
+                (94853, "/a".to_string()),
+                (24933642, "/d".to_string()),
+            ],
+            du(&tree, SortBy::Name, Some(1))
+        );
+        assert_eq!(
+            2,
+            tree.depth(tree.child(tree.child(0, "a").unwrap(), "e").unwrap())
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{is_valid_name, FileMetaData, FileSystem, NodeId};
+
+/// Emit a terminal session in the puzzle format which rebuilds `tree` when
+/// fed to `build_tree`. Unexplored directories are never listed; they are
+/// entered when they have sub-directories, or when no listing of their
+/// parent shows them.
+pub fn to_transcript(tree: &FileSystem) -> String {
+    let mut lines = vec!["$ cd /".to_string()];
+    write_dir(tree, FileSystem::ROOT, &mut lines);
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn write_dir(tree: &FileSystem, id: NodeId, lines: &mut Vec<String>) {
+    let node = &tree.nodes[id];
+    if node.listed {
+        lines.push("$ ls".to_string());
+        let mut entries: Vec<(&str, String)> = node
+            .children
+            .iter()
+            .map(|&child| {
+                let name = tree.nodes[child].name.as_str();
+                (name, format!("dir {}", name))
+            })
+            .chain(
+                node.files
+                    .iter()
+                    .map(|f| (f.name.as_str(), format!("{} {}", f.size, f.name))),
+            )
+            .collect();
+        entries.sort();
+        lines.extend(entries.into_iter().map(|(_, line)| line));
+    }
+
+    let mut children = node.children.clone();
+    children.sort_by(|&a, &b| tree.nodes[a].name.cmp(&tree.nodes[b].name));
+    for child in children {
+        let child_node = &tree.nodes[child];
+        // a directory the parent's listing shows, with nothing further to
+        // record, needs no visit
+        if child_node.listed || !child_node.children.is_empty() || !node.listed {
+            lines.push(format!("$ cd {}", child_node.name));
+            write_dir(tree, child, lines);
+            lines.push("$ cd ..".to_string());
+        }
+    }
+}
+
+/// Walk a real directory on disk into a tree. Symbolic links are skipped so
+/// that the walk always terminates. Entries whose names a terminal session
+/// cannot express, such as names with spaces, are left out and returned
+/// alongside the tree.
+pub fn from_disk(root: &Path) -> io::Result<(FileSystem, Vec<PathBuf>)> {
+    let mut tree = FileSystem::new();
+    let mut skipped = vec![];
+    walk_dir(&mut tree, FileSystem::ROOT, root, &mut skipped)?;
+    tree.update_sizes();
+    Ok((tree, skipped))
+}
+
+fn walk_dir(
+    tree: &mut FileSystem,
+    id: NodeId,
+    dir: &Path,
+    skipped: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    tree.nodes[id].listed = true;
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = match entry.file_name().into_string() {
+            Ok(name) if is_valid_name(&name) => name,
+            _ => {
+                skipped.push(entry.path());
+                continue;
+            }
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let child = tree.add_dir(id, name);
+            walk_dir(tree, child, &entry.path(), skipped)?;
+        } else if file_type.is_file() {
+            let size = entry.metadata()?.len();
+            tree.nodes[id].files.push(FileMetaData { name, size });
+        }
+    }
+    Ok(())
+}
+
+/// Advance a linear congruential generator and return a number below
+/// `bound`, so that generated trees are the same on every run.
+fn next_random(seed: &mut u64, bound: u64) -> u64 {
+    *seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    (*seed >> 33) % bound.max(1)
+}
+
+/// Deterministic pseudo-random tree of `n_dirs` directories, each holding
+/// up to `max_files` files, for producing large synthetic sessions.
+pub fn synthetic_tree(n_dirs: usize, max_files: usize, mut seed: u64) -> FileSystem {
+    let mut next = |bound: u64| next_random(&mut seed, bound);
+    let mut tree = FileSystem::new();
+    tree.nodes[FileSystem::ROOT].listed = true;
+    for idx in 0..n_dirs {
+        let parent = next(tree.nodes.len() as u64) as NodeId;
+        let dir = tree.add_dir(parent, format!("d{}", idx));
+        tree.nodes[dir].listed = true;
+    }
+    for id in 0..tree.nodes.len() {
+        for idx in 0..next(max_files as u64 + 1) {
+            let size = 1 + next(300_000);
+            tree.nodes[id].files.push(FileMetaData {
+                name: format!("f{}.dat", idx),
+                size,
+            });
+        }
+    }
+    tree.update_sizes();
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tree;
+    use crate::report::render_tree;
+
+    #[test]
+    fn test_to_transcript() {
+        let sample = include_str!("../../sample_input.txt");
+        let tree = build_tree(sample).unwrap();
+        let transcript = to_transcript(&tree);
+        assert_eq!(
+            "$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d\n$ cd a\n$ ls\ndir e\n\
+             29116 f\n2557 g\n62596 h.lst\n$ cd e\n$ ls\n584 i\n$ cd ..\n$ cd ..\n$ cd d\n$ ls\n\
+             5626152 d.ext\n8033020 d.log\n4060174 j\n7214296 k\n$ cd ..\n",
+            transcript
+        );
+
+        let rebuilt = build_tree(&transcript).unwrap();
+        assert_eq!(render_tree(&tree, None), render_tree(&rebuilt, None));
+    }
+
+    #[test]
+    fn test_unexplored_round_trip() {
+        let tree = build_tree("$ ls\ndir a\ndir b\n$ cd b/c\n$ ls\n5 f\n").unwrap();
+        let rebuilt = build_tree(&to_transcript(&tree)).unwrap();
+        assert_eq!(render_tree(&tree, None), render_tree(&rebuilt, None));
+        assert_eq!(2, rebuilt.unexplored_dirs().len());
+
+        // directories only ever entered, below a parent never listed
+        for session in ["$ cd a\n", "$ cd a/b\n$ cd /c\n$ ls\n"] {
+            let tree = build_tree(session).unwrap();
+            let transcript = to_transcript(&tree);
+            let rebuilt = build_tree(&transcript).unwrap();
+            assert_eq!(render_tree(&tree, None), render_tree(&rebuilt, None));
+        }
+        assert_eq!(
+            "$ cd /\n$ cd a\n$ cd ..\n",
+            to_transcript(&build_tree("$ cd a\n").unwrap())
+        );
+
+        // files put in directories never listed make them listed
+        let session = "$ ls\ndir a\ndir b\n2 f\n$ touch a/z\n$ cd b/c\n$ cd /\n$ mv f b/c\n";
+        let tree = build_tree(session).unwrap();
+        let rebuilt = build_tree(&to_transcript(&tree)).unwrap();
+        assert_eq!(render_tree(&tree, None), render_tree(&rebuilt, None));
+        let unexplored: Vec<String> = tree
+            .unexplored_dirs()
+            .into_iter()
+            .map(|id| tree.path(id))
+            .collect();
+        assert_eq!(vec!["/b"], unexplored);
+    }
+
+    #[test]
+    fn test_synthetic_round_trip() {
+        let tree = synthetic_tree(500, 6, 11);
+        assert_eq!(501, tree.nodes.len());
+        let rebuilt = build_tree(&to_transcript(&tree)).unwrap();
+        assert_eq!(render_tree(&tree, None), render_tree(&rebuilt, None));
+    }
+
+    #[test]
+    fn test_from_disk() {
+        let root = std::env::temp_dir().join(format!("day_7_from_disk_{}", std::process::id()));
+        fs::create_dir_all(root.join("a/e")).unwrap();
+        fs::create_dir_all(root.join("d")).unwrap();
+        fs::write(root.join("b.txt"), vec![0; 120]).unwrap();
+        fs::write(root.join("a/f"), vec![0; 30]).unwrap();
+        fs::write(root.join("a/e/i"), vec![0; 5]).unwrap();
+        fs::create_dir_all(root.join("my dir")).unwrap();
+        fs::write(root.join("my dir/x"), vec![0; 7]).unwrap();
+        fs::write(root.join("a/a file.txt"), vec![0; 9]).unwrap();
+        fs::create_dir_all(root.join("-x")).unwrap();
+
+        let (tree, skipped) = from_disk(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(
+            vec![
+                root.join("-x"),
+                root.join("a/a file.txt"),
+                root.join("my dir")
+            ],
+            skipped
+        );
+        let rebuilt = build_tree(&to_transcript(&tree)).unwrap();
+        assert_eq!(render_tree(&tree, None), render_tree(&rebuilt, None));
+        assert_eq!(
+            "- / (dir, size=155)\n  - a (dir, size=35)\n    - e (dir, size=5)\n      \
+             - i (file, size=5)\n    - f (file, size=30)\n  - b.txt (file, size=120)\n  \
+             - d (dir, size=0)",
+            render_tree(&tree, None)
+        );
+        assert!(from_disk(&root).is_err());
+    }
+}
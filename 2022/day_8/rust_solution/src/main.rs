@@ -1,4 +1,15 @@
-use std::{fs, vec};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+mod render;
+mod sight;
+
+use render::Image;
+use sight::{Position, ALL_DIRECTIONS, CARDINAL};
 
 struct Matrix {
     pub matrix: Vec<Vec<u32>>,
@@ -9,14 +20,42 @@ impl Matrix {
         Matrix { matrix }
     }
 
-    fn n_rows(&self) -> usize {
-        self.matrix.len()
+    /// Parse one row of digits per line. Every row must be as long as the
+    /// first, and blank lines are only allowed at the end.
+    pub fn parse(contents: &str) -> Result<Matrix, ParseError> {
+        let mut matrix: Vec<Vec<u32>> = vec![];
+        for (idx, line) in contents.trim_end().lines().enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let row = line
+                .chars()
+                .enumerate()
+                .map(|(col, c)| {
+                    c.to_digit(10).ok_or(ParseError::InvalidDigit {
+                        line: idx + 1,
+                        column: col + 1,
+                        found: c,
+                    })
+                })
+                .collect::<Result<Vec<u32>, ParseError>>()?;
+            if let Some(first) = matrix.first() {
+                if first.len() != row.len() {
+                    return Err(ParseError::RaggedRow {
+                        line: idx + 1,
+                        expected: first.len(),
+                        found: row.len(),
+                    });
+                }
+            }
+            matrix.push(row);
+        }
+        Ok(Matrix::new(matrix))
     }
 
     fn n_columns(&self) -> usize {
-        self.matrix[0].len()
+        self.matrix.first().map_or(0, |row| row.len())
     }
 
+    #[cfg(test)]
     fn get_column(&self, col: usize) -> Vec<u32> {
         self.matrix
             .iter()
@@ -24,10 +63,12 @@ impl Matrix {
             .collect()
     }
 
+    #[cfg(test)]
     fn visible_to_left(&self, vector: &[u32], idx: usize) -> bool {
         vector[0..idx].iter().all(|n| n < &vector[idx])
     }
 
+    #[cfg(test)]
     fn score_to_left(&self, vector: &[u32], idx: usize) -> usize {
         match vector[0..idx].iter().rev().position(|n| n >= &vector[idx]) {
             Some(position) => position + 1,
@@ -35,12 +76,14 @@ impl Matrix {
         }
     }
 
+    #[cfg(test)]
     fn visible_to_right(&self, vector: &[u32], idx: usize) -> bool {
         vector[idx + 1..vector.len()]
             .iter()
             .all(|n| n < &vector[idx])
     }
 
+    #[cfg(test)]
     fn score_to_right(&self, vector: &[u32], idx: usize) -> usize {
         match vector[idx + 1..vector.len()]
             .iter()
@@ -51,10 +94,11 @@ impl Matrix {
         }
     }
 
+    #[cfg(test)]
     pub fn score(&self, row: usize, col: usize) -> usize {
         // score from row
         let vector = &self.matrix[row];
-        let row_score = self.score_to_left(&vector, col) * self.score_to_right(&vector, col);
+        let row_score = self.score_to_left(vector, col) * self.score_to_right(vector, col);
 
         // score from column
         let vector = self.get_column(col);
@@ -63,57 +107,396 @@ impl Matrix {
         row_score * col_score
     }
 
+    #[cfg(test)]
     pub fn is_visible_in_row(&self, row: usize, col: usize) -> bool {
         let left_side = self.visible_to_left(&self.matrix[row], col);
         let right_side = self.visible_to_right(&self.matrix[row], col);
         left_side || right_side
     }
 
+    #[cfg(test)]
     pub fn is_visible_in_col(&self, row: usize, col: usize) -> bool {
         let column = self.get_column(col);
         let upper_side = self.visible_to_left(&column, row);
         let lower_side = self.visible_to_right(&column, row);
         upper_side || lower_side
     }
+
+    /// Whether each tree can be seen from outside the grid, found with one
+    /// sweep per direction that keeps the tallest tree seen so far.
+    pub fn visibility_map(&self) -> Vec<Vec<bool>> {
+        let mut visible: Vec<Vec<bool>> = self
+            .matrix
+            .iter()
+            .map(|row| vec![false; row.len()])
+            .collect();
+
+        for (row, flags) in self.matrix.iter().zip(visible.iter_mut()) {
+            let mut tallest = None;
+            for (height, flag) in row.iter().zip(flags.iter_mut()) {
+                if tallest < Some(height) {
+                    *flag = true;
+                    tallest = Some(height);
+                }
+            }
+            let mut tallest = None;
+            for (height, flag) in row.iter().zip(flags.iter_mut()).rev() {
+                if tallest < Some(height) {
+                    *flag = true;
+                    tallest = Some(height);
+                }
+            }
+        }
+
+        // sweep whole rows at a time, keeping one running maximum per column
+        let mut tallest = vec![None; self.n_columns()];
+        for (row, flags) in self.matrix.iter().zip(visible.iter_mut()) {
+            for ((height, flag), tallest) in row.iter().zip(flags.iter_mut()).zip(&mut tallest) {
+                if *tallest < Some(height) {
+                    *flag = true;
+                    *tallest = Some(height);
+                }
+            }
+        }
+        let mut tallest = vec![None; self.n_columns()];
+        for (row, flags) in self.matrix.iter().zip(visible.iter_mut()).rev() {
+            for ((height, flag), tallest) in row.iter().zip(flags.iter_mut()).zip(&mut tallest) {
+                if *tallest < Some(height) {
+                    *flag = true;
+                    *tallest = Some(height);
+                }
+            }
+        }
+        visible
+    }
+
+    /// Scenic score of every tree, using one monotonic stack per row or
+    /// column and direction.
+    pub fn scenic_scores(&self) -> Vec<Vec<usize>> {
+        let mut scores: Vec<Vec<usize>> = self
+            .matrix
+            .iter()
+            .map(|row| {
+                let left = viewing_distances(row.iter().copied());
+                let mut right = viewing_distances(row.iter().rev().copied());
+                right.reverse();
+                left.iter().zip(right).map(|(l, r)| l * r).collect()
+            })
+            .collect();
+
+        // walk whole rows at a time, keeping one stack per column
+        let mut blocking = vec![vec![]; self.n_columns()];
+        for (row, (heights, scores)) in self.matrix.iter().zip(scores.iter_mut()).enumerate() {
+            for ((&height, score), blocking) in heights.iter().zip(scores).zip(&mut blocking) {
+                *score *= look_back(blocking, row, height);
+            }
+        }
+        let mut blocking = vec![vec![]; self.n_columns()];
+        let rows = self.matrix.iter().zip(scores.iter_mut()).rev();
+        for (row, (heights, scores)) in rows.enumerate() {
+            for ((&height, score), blocking) in heights.iter().zip(scores).zip(&mut blocking) {
+                *score *= look_back(blocking, row, height);
+            }
+        }
+        scores
+    }
+
+    /// Position and score of the tree with the highest scenic score, the
+    /// first one in reading order on ties.
+    pub fn best_spot(&self) -> Option<((usize, usize), usize)> {
+        let mut best: Option<((usize, usize), usize)> = None;
+        for (row, scores) in self.scenic_scores().iter().enumerate() {
+            for (col, &score) in scores.iter().enumerate() {
+                if best.is_none_or(|(_, max)| score > max) {
+                    best = Some(((row, col), score));
+                }
+            }
+        }
+        best
+    }
+}
+
+/// How many trees each tree sees looking back towards the start of the
+/// line, stopping at the first one at least as tall.
+fn viewing_distances(heights: impl Iterator<Item = u32>) -> Vec<usize> {
+    let mut blocking = vec![];
+    heights
+        .enumerate()
+        .map(|(idx, height)| look_back(&mut blocking, idx, height))
+        .collect()
+}
+
+// Viewing distance of the tree at `idx`, given the positions and heights of
+// the earlier trees which can still block a view, tallest first.
+fn look_back(blocking: &mut Vec<(usize, u32)>, idx: usize, height: u32) -> usize {
+    while blocking.last().is_some_and(|&(_, h)| h < height) {
+        blocking.pop();
+    }
+    let distance = match blocking.last() {
+        Some(&(blocker, _)) => idx - blocker,
+        None => idx,
+    };
+    blocking.push((idx, height));
+    distance
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    /// 1-based position of a character which is not a height.
+    InvalidDigit {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidDigit {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {}, column {}: {:?} is not a tree height",
+                line, column, found
+            ),
+            ParseError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} trees like the first row, found {}",
+                line, expected, found
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Which grid to draw with `--render`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Heights,
+    Visibility,
+    Scores,
+}
+
+/// How `--treehouse` combines the viewing distances of a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scoring {
+    /// The puzzle's scenic score.
+    Product,
+    /// How many trees can be seen in total.
+    Sum,
+    /// The shortest view, for a treehouse without a bad side.
+    Min,
+}
+
+impl Scoring {
+    fn score(self, distances: &[usize]) -> usize {
+        match self {
+            Scoring::Product => distances.iter().product(),
+            Scoring::Sum => distances.iter().sum(),
+            Scoring::Min => distances.iter().copied().min().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    Ansi,
+    Ppm,
+    Png,
+}
+
+struct Config {
+    pub filename: String,
+    pub render: Option<Layer>,
+    pub format: ImageFormat,
+    pub output: Option<String>,
+    pub scale: usize,
+    pub from: Option<Position>,
+    pub height: Option<u32>,
+    pub treehouse: Option<Scoring>,
+    pub diagonals: bool,
+}
+
+impl Config {
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
+        // iter past name
+        args.next();
+
+        let mut config = Config {
+            filename: "../input.txt".to_string(),
+            render: None,
+            format: ImageFormat::Ansi,
+            output: None,
+            scale: 1,
+            from: None,
+            height: None,
+            treehouse: None,
+            diagonals: false,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--render" => {
+                    config.render = match args.next().as_deref() {
+                        Some("heights") => Some(Layer::Heights),
+                        Some("visibility") => Some(Layer::Visibility),
+                        Some("scores") => Some(Layer::Scores),
+                        _ => return Err("--render takes heights, visibility or scores"),
+                    }
+                }
+                "--format" => {
+                    config.format = match args.next().as_deref() {
+                        Some("ansi") => ImageFormat::Ansi,
+                        Some("ppm") => ImageFormat::Ppm,
+                        Some("png") => ImageFormat::Png,
+                        _ => return Err("--format takes ansi, ppm or png"),
+                    }
+                }
+                "--output" => {
+                    config.output = Some(args.next().ok_or("Missing value for --output")?);
+                }
+                "--scale" => {
+                    let scale = args.next().ok_or("Missing value for --scale")?;
+                    config.scale = match scale.parse() {
+                        Ok(scale) if scale > 0 => scale,
+                        _ => return Err("Invalid value for --scale"),
+                    };
+                }
+                "--from" => {
+                    let position = args.next().ok_or("Missing value for --from")?;
+                    let (row, col) = position
+                        .split_once(',')
+                        .ok_or("--from takes a row and column like 3,2")?;
+                    config.from = Some((
+                        row.parse().map_err(|_| "Invalid row for --from")?,
+                        col.parse().map_err(|_| "Invalid column for --from")?,
+                    ));
+                }
+                "--height" => {
+                    let height = args.next().ok_or("Missing value for --height")?;
+                    config.height = Some(height.parse().map_err(|_| "Invalid value for --height")?);
+                }
+                "--treehouse" => {
+                    config.treehouse = match args.next().as_deref() {
+                        Some("product") => Some(Scoring::Product),
+                        Some("sum") => Some(Scoring::Sum),
+                        Some("min") => Some(Scoring::Min),
+                        _ => return Err("--treehouse takes product, sum or min"),
+                    }
+                }
+                "--diagonals" => config.diagonals = true,
+                _ if arg.starts_with("--") => return Err("Unknown option"),
+                _ => config.filename = arg,
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn render(matrix: &Matrix, config: &Config, layer: Layer) -> io::Result<()> {
+    let image = match layer {
+        Layer::Heights => render::heights_image(matrix),
+        Layer::Visibility => render::visibility_image(&matrix.visibility_map()),
+        Layer::Scores => render::scores_image(&matrix.scenic_scores()),
+    };
+    let image: Image = image.scaled(config.scale);
+    let bytes = match config.format {
+        ImageFormat::Ansi => image.to_ansi().into_bytes(),
+        ImageFormat::Ppm => image.to_ppm(),
+        ImageFormat::Png => image.to_png(),
+    };
+    match &config.output {
+        Some(filename) => fs::write(filename, bytes),
+        None => io::stdout().write_all(&bytes),
+    }
 }
 
 fn main() {
+    let config = Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        process::exit(1);
+    });
+
     // get content
-    let contents = fs::read_to_string("../input.txt").expect("Missing input file");
+    let contents = fs::read_to_string(&config.filename).expect("Missing input file");
 
     // Parse the contents of the file as a matrix of numbers
-    let matrix: Vec<Vec<u32>> = contents
-        .lines()
-        .map(|line| {
-            line.split("")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse().unwrap())
-                .collect()
-        })
-        .collect();
-
-    let matrix = Matrix::new(matrix);
-
-    let mut visible_count = matrix.n_columns() * 2 + matrix.n_rows() * 2 - 4;
-    for col in 1..matrix.n_columns() - 1 {
-        for row in 1..matrix.n_rows() - 1 {
-            if matrix.is_visible_in_col(row, col) || matrix.is_visible_in_row(row, col) {
-                visible_count += 1
+    let matrix = Matrix::parse(&contents).unwrap_or_else(|err| {
+        eprintln!("Invalid forest: {}", err);
+        process::exit(1);
+    });
+
+    if let Some(layer) = config.render {
+        if let Err(err) = render(&matrix, &config, layer) {
+            eprintln!("Unable to write image: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let directions: &[(isize, isize)] = if config.diagonals {
+        &ALL_DIRECTIONS
+    } else {
+        &CARDINAL
+    };
+    if let Some((row, col)) = config.from {
+        let Some(&own_height) = matrix.matrix.get(row).and_then(|heights| heights.get(col)) else {
+            eprintln!("({}, {}) is outside the forest", row, col);
+            process::exit(1);
+        };
+        let height = config.height.unwrap_or(own_height);
+        let seen = matrix.visible_from((row, col), height, directions);
+        println!(
+            "{} trees visible from ({}, {}) at height {}:",
+            seen.len(),
+            row,
+            col,
+            height
+        );
+        for (row, col) in seen {
+            println!("  ({}, {}) height {}", row, col, matrix.matrix[row][col]);
+        }
+        return;
+    }
+    if let Some(scoring) = config.treehouse {
+        match matrix.best_cell(directions, |_, distances| scoring.score(distances)) {
+            Some(((row, col), score)) => {
+                println!(
+                    "Best treehouse at row {}, column {} scores {}",
+                    row, col, score
+                )
             }
+            None => println!("The forest is empty"),
         }
+        return;
     }
+
+    let visible_count = matrix
+        .visibility_map()
+        .iter()
+        .flatten()
+        .filter(|&&visible| visible)
+        .count();
     println!("Visible Count: {}", visible_count);
 
-    let mut max = 0;
-    for col in 1..matrix.n_columns() - 1 {
-        for row in 1..matrix.n_rows() - 1 {
-            let current_score = matrix.score(row, col);
-            if current_score > max {
-                max = current_score;
-            }
+    match matrix.best_spot() {
+        Some(((row, col), max)) => {
+            println!("The max score is {} at row {}, column {}", max, row, col)
         }
+        None => println!("The forest is empty"),
     }
-    println!("The max score is {}", max)
 }
 
 #[cfg(test)]
@@ -164,34 +547,34 @@ mod tests {
         let matrix = Matrix::new(matrix);
 
         let row = &matrix.matrix[1];
-        assert_eq!(1, matrix.score_to_left(&row, 2));
+        assert_eq!(1, matrix.score_to_left(row, 2));
 
         let row = &matrix.matrix[3];
-        assert_eq!(2, matrix.score_to_left(&row, 2));
+        assert_eq!(2, matrix.score_to_left(row, 2));
 
         let row = &matrix.matrix[1];
-        assert_eq!(2, matrix.score_to_right(&row, 2));
+        assert_eq!(2, matrix.score_to_right(row, 2));
 
         let row = &matrix.matrix[3];
-        assert_eq!(2, matrix.score_to_right(&row, 2));
+        assert_eq!(2, matrix.score_to_right(row, 2));
 
         let row = &matrix.matrix[4];
-        assert_eq!(1, matrix.score_to_left(&row, 2));
+        assert_eq!(1, matrix.score_to_left(row, 2));
 
         let row = &matrix.matrix[4];
-        assert_eq!(1, matrix.score_to_right(&row, 2));
+        assert_eq!(1, matrix.score_to_right(row, 2));
 
         let row = &matrix.matrix[2];
-        assert_eq!(1, matrix.score_to_left(&row, 1));
+        assert_eq!(1, matrix.score_to_left(row, 1));
 
         let row = &matrix.matrix[2];
-        assert_eq!(3, matrix.score_to_right(&row, 1));
+        assert_eq!(3, matrix.score_to_right(row, 1));
 
         let row = &matrix.matrix[4];
-        assert_eq!(1, matrix.score_to_left(&row, 1));
+        assert_eq!(1, matrix.score_to_left(row, 1));
 
         let row = &matrix.matrix[4];
-        assert_eq!(2, matrix.score_to_right(&row, 1));
+        assert_eq!(2, matrix.score_to_right(row, 1));
     }
 
     #[test]
@@ -212,6 +595,105 @@ mod tests {
         assert_eq!(8, matrix.score(3, 2));
     }
 
+    #[test]
+    fn test_scenic_scores() {
+        let matrix: Vec<Vec<u32>> = [
+            [3, 0, 3, 7, 3].to_vec(),
+            [2, 5, 5, 1, 2].to_vec(),
+            [6, 5, 3, 3, 2].to_vec(),
+            [3, 3, 5, 4, 9].to_vec(),
+            [3, 5, 3, 9, 0].to_vec(),
+        ]
+        .to_vec();
+
+        let matrix = Matrix::new(matrix);
+
+        let scores = matrix.scenic_scores();
+        assert_eq!(4, scores[1][2]);
+        assert_eq!(8, scores[3][2]);
+        assert_eq!(vec![0; 5], scores[0]);
+        for (row, scores) in scores.iter().enumerate() {
+            for (col, &score) in scores.iter().enumerate() {
+                assert_eq!(matrix.score(row, col), score);
+            }
+        }
+        assert_eq!(Some(((3, 2), 8)), matrix.best_spot());
+
+        assert_eq!(
+            vec![0, 1, 2, 3, 1],
+            viewing_distances([3, 0, 3, 7, 3].into_iter())
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let matrix = Matrix::parse(include_str!("../../sample_input.txt")).unwrap();
+        assert_eq!(vec![2, 5, 5, 1, 2], matrix.matrix[1]);
+        assert_eq!(5, matrix.matrix.len());
+
+        let matrix = Matrix::parse("12\r\n34\r\n\n\n").unwrap();
+        assert_eq!(vec![vec![1, 2], vec![3, 4]], matrix.matrix);
+
+        assert_eq!(
+            Err(ParseError::InvalidDigit {
+                line: 2,
+                column: 3,
+                found: 'x'
+            }),
+            Matrix::parse("123\n45x\n").map(|m| m.matrix)
+        );
+        assert_eq!(
+            Err(ParseError::RaggedRow {
+                line: 3,
+                expected: 3,
+                found: 2
+            }),
+            Matrix::parse("123\n456\n78\n").map(|m| m.matrix)
+        );
+        assert_eq!(
+            Err(ParseError::RaggedRow {
+                line: 2,
+                expected: 2,
+                found: 0
+            }),
+            Matrix::parse("12\n\n34\n").map(|m| m.matrix)
+        );
+        let err = Matrix::parse("1 2").err().unwrap();
+        assert_eq!(
+            "line 1, column 2: ' ' is not a tree height",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_degenerate_shapes() {
+        let count = |matrix: &Matrix| {
+            let visible = matrix.visibility_map();
+            visible.iter().flatten().filter(|&&v| v).count()
+        };
+
+        let empty = Matrix::parse("").unwrap();
+        assert_eq!(0, count(&empty));
+        assert!(empty.scenic_scores().is_empty());
+        assert_eq!(None, empty.best_spot());
+
+        let row = Matrix::parse("30373").unwrap();
+        assert_eq!(5, count(&row));
+        assert_eq!(vec![vec![0; 5]], row.scenic_scores());
+        assert_eq!(Some(((0, 0), 0)), row.best_spot());
+
+        let column = Matrix::parse("3\n0\n3\n7\n3\n").unwrap();
+        assert_eq!(5, count(&column));
+        assert_eq!(vec![vec![0]; 5], column.scenic_scores());
+
+        let single = Matrix::parse("5").unwrap();
+        assert_eq!(1, count(&single));
+        assert_eq!(Some(((0, 0), 0)), single.best_spot());
+
+        let two_rows = Matrix::parse("111\n111").unwrap();
+        assert_eq!(6, count(&two_rows));
+    }
+
     #[test]
     fn test_matrix_get_column() {
         let matrix: Vec<Vec<u32>> = [
@@ -228,6 +710,63 @@ mod tests {
         assert_eq!([3, 2, 6, 3, 3].to_vec(), matrix.get_column(0))
     }
 
+    #[test]
+    fn test_visibility_map() {
+        let matrix: Vec<Vec<u32>> = [
+            [3, 0, 3, 7, 3].to_vec(),
+            [2, 5, 5, 1, 2].to_vec(),
+            [6, 5, 3, 3, 2].to_vec(),
+            [3, 3, 5, 4, 9].to_vec(),
+            [3, 5, 3, 9, 0].to_vec(),
+        ]
+        .to_vec();
+
+        let matrix = Matrix::new(matrix);
+
+        let visible = matrix.visibility_map();
+        assert_eq!(vec![true, true, true, false, true], visible[1]);
+        assert_eq!(vec![true, false, true, false, true], visible[3]);
+        assert_eq!(21, visible.iter().flatten().filter(|&&v| v).count());
+    }
+
+    // Linear congruential generator, so random grids repeat between runs.
+    fn next_random(seed: &mut u64, bound: u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) % bound
+    }
+
+    #[test]
+    fn test_sweeps_match_per_cell() {
+        let mut seed: u64 = 8;
+        let mut next = |bound| next_random(&mut seed, bound);
+        for _ in 0..200 {
+            let (n_rows, n_columns) = (next(7) as usize + 1, next(7) as usize + 1);
+            let max_height = next(10) + 1;
+            let matrix: Vec<Vec<u32>> = (0..n_rows)
+                .map(|_| (0..n_columns).map(|_| next(max_height) as u32).collect())
+                .collect();
+            let matrix = Matrix::new(matrix);
+
+            let visible = matrix.visibility_map();
+            for (row, flags) in visible.iter().enumerate() {
+                for (col, &flag) in flags.iter().enumerate() {
+                    let expected =
+                        matrix.is_visible_in_row(row, col) || matrix.is_visible_in_col(row, col);
+                    assert_eq!(expected, flag, "{:?} at ({}, {})", matrix.matrix, row, col);
+                }
+            }
+
+            let scores = matrix.scenic_scores();
+            for (row, scores) in scores.iter().enumerate() {
+                for (col, &score) in scores.iter().enumerate() {
+                    assert_eq!(matrix.score(row, col), score);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_matrix_column_visibility() {
         let matrix: Vec<Vec<u32>> = [
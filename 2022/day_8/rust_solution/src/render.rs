@@ -0,0 +1,304 @@
+use crate::Matrix;
+
+/// An RGB image, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    /// One pixel per grid cell, colored by `color`.
+    pub fn from_grid<T>(grid: &[Vec<T>], color: impl Fn(&T) -> [u8; 3]) -> Image {
+        Image {
+            width: grid.first().map_or(0, |row| row.len()),
+            height: grid.len(),
+            pixels: grid.iter().flatten().map(color).collect(),
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Blow every pixel up into a `factor`×`factor` square.
+    pub fn scaled(&self, factor: usize) -> Image {
+        let (width, height) = (self.width * factor, self.height * factor);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x / factor, y / factor)))
+            .map(|(x, y)| self.pixel(x, y))
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Binary PPM (`P6`), readable by most image viewers.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.pixels.iter().flatten());
+        bytes
+    }
+
+    /// 8-bit RGB PNG. The image data is stored without compression, which
+    /// keeps the encoder short and is plenty for puzzle-sized grids.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = vec![];
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // bit depth 8, truecolor, default compression, filter and interlace
+        header.extend([8, 2, 0, 0, 0]);
+
+        // every scanline starts with filter type 0 (none)
+        let mut raw = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for y in 0..self.height {
+            raw.push(0);
+            raw.extend(
+                self.pixels[y * self.width..(y + 1) * self.width]
+                    .iter()
+                    .flatten(),
+            );
+        }
+
+        let mut bytes = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        write_chunk(&mut bytes, b"IHDR", &header);
+        write_chunk(&mut bytes, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+
+    /// Colored terminal output using 24-bit ANSI escapes. Each character
+    /// shows two pixel rows: the upper half block takes the foreground color
+    /// and the background shows through below it.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let [r, g, b] = self.pixel(x, y);
+                out += &format!("\x1b[38;2;{};{};{}m", r, g, b);
+                if y + 1 < self.height {
+                    let [r, g, b] = self.pixel(x, y + 1);
+                    out += &format!("\x1b[48;2;{};{};{}m", r, g, b);
+                } else {
+                    out += "\x1b[49m";
+                }
+                out.push('▀');
+            }
+            out += "\x1b[0m\n";
+        }
+        out
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend((data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend(kind);
+    bytes.extend(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend(crc.to_be_bytes());
+}
+
+// A zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        bytes.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        bytes.push(last as u8);
+        let len = block.len() as u16;
+        bytes.extend(len.to_le_bytes());
+        bytes.extend((!len).to_le_bytes());
+        bytes.extend(block);
+    }
+    bytes.extend(adler32(data).to_be_bytes());
+    bytes
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Color for a value between 0 and 1, running from dark blue through green
+/// to yellow.
+pub fn heat(fraction: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 4] = [
+        [20.0, 20.0, 80.0],
+        [30.0, 120.0, 160.0],
+        [60.0, 190.0, 80.0],
+        [250.0, 230.0, 60.0],
+    ];
+    let position = fraction.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let idx = (position as usize).min(STOPS.len() - 2);
+    let t = position - idx as f64;
+    let mut color = [0; 3];
+    for (channel, value) in color.iter_mut().enumerate() {
+        let (from, to) = (STOPS[idx][channel], STOPS[idx + 1][channel]);
+        *value = (from + (to - from) * t).round() as u8;
+    }
+    color
+}
+
+/// Tree heights, 0 darkest and 9 brightest.
+pub fn heights_image(matrix: &Matrix) -> Image {
+    Image::from_grid(&matrix.matrix, |&height| heat(height as f64 / 9.0))
+}
+
+/// Visible trees in green, hidden ones in dark grey.
+pub fn visibility_image(visible: &[Vec<bool>]) -> Image {
+    Image::from_grid(
+        visible,
+        |&visible| {
+            if visible {
+                [60, 200, 80]
+            } else {
+                [40, 40, 40]
+            }
+        },
+    )
+}
+
+/// Scenic scores on a logarithmic scale, since a handful of trees score
+/// orders of magnitude higher than the rest.
+pub fn scores_image(scores: &[Vec<usize>]) -> Image {
+    let max = scores.iter().flatten().copied().max().unwrap_or(0);
+    let scale = (max as f64).ln_1p().max(1.0);
+    Image::from_grid(scores, |&score| heat((score as f64).ln_1p() / scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Image {
+        Image::from_grid(&[vec![0u8, 1, 2], vec![3, 4, 5]], |&v| [v, v * 10, 255 - v])
+    }
+
+    // Undo `zlib_stored`, checking every length field and the checksum.
+    fn unzlib_stored(bytes: &[u8]) -> Vec<u8> {
+        assert_eq!([0x78, 0x01], bytes[..2]);
+        let mut data = vec![];
+        let mut pos = 2;
+        loop {
+            let last = bytes[pos] & 1 == 1;
+            assert_eq!(0, bytes[pos] >> 1);
+            let len = u16::from_le_bytes([bytes[pos + 1], bytes[pos + 2]]);
+            let nlen = u16::from_le_bytes([bytes[pos + 3], bytes[pos + 4]]);
+            assert_eq!(!len, nlen);
+            data.extend(&bytes[pos + 5..pos + 5 + len as usize]);
+            pos += 5 + len as usize;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(adler32(&data).to_be_bytes(), bytes[pos..pos + 4]);
+        assert_eq!(pos + 4, bytes.len());
+        data
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(0xcbf43926, crc32(b"123456789"));
+        assert_eq!(0x11e60398, adler32(b"Wikipedia"));
+
+        let data: Vec<u8> = (0..200000).map(|i| (i % 251) as u8).collect();
+        assert_eq!(data, unzlib_stored(&zlib_stored(&data)));
+        assert!(unzlib_stored(&zlib_stored(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_png() {
+        let png = sample().to_png();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+
+        let mut chunks = vec![];
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let body = &png[pos + 4..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc);
+            chunks.push((body[..4].to_vec(), body[4..].to_vec()));
+            pos += 12 + len;
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(vec![&b"IHDR"[..], b"IDAT", b"IEND"], kinds);
+        assert_eq!([0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0], chunks[0].1[..]);
+        let expected = [
+            0, 0, 0, 255, 1, 10, 254, 2, 20, 253, //
+            0, 3, 30, 252, 4, 40, 251, 5, 50, 250,
+        ];
+        assert_eq!(expected[..], unzlib_stored(&chunks[1].1)[..]);
+    }
+
+    #[test]
+    fn test_ppm_and_scaling() {
+        let image = sample().scaled(2);
+        assert_eq!((6, 4), (image.width, image.height));
+        assert_eq!([1, 10, 254], image.pixel(3, 1));
+        assert_eq!([5, 50, 250], image.pixel(5, 3));
+
+        let ppm = sample().to_ppm();
+        assert_eq!(b"P6\n3 2\n255\n", &ppm[..11]);
+        assert_eq!([0, 0, 255, 1, 10, 254], ppm[11..17]);
+        assert_eq!(11 + 18, ppm.len());
+    }
+
+    #[test]
+    fn test_ansi() {
+        let ansi = sample().to_ansi();
+        assert_eq!(1, ansi.lines().count());
+        assert!(ansi.starts_with("\x1b[38;2;0;0;255m\x1b[48;2;3;30;252m▀"));
+        assert_eq!(3, ansi.matches('▀').count());
+
+        let odd = Image::from_grid(&[vec![1u8], vec![2], vec![3]], |&v| [v, v, v]);
+        assert_eq!(
+            "\x1b[38;2;1;1;1m\x1b[48;2;2;2;2m▀\x1b[0m\n\x1b[38;2;3;3;3m\x1b[49m▀\x1b[0m\n",
+            odd.to_ansi()
+        );
+    }
+
+    #[test]
+    fn test_layers() {
+        assert_eq!([20, 20, 80], heat(0.0));
+        assert_eq!([250, 230, 60], heat(1.0));
+        assert_eq!([250, 230, 60], heat(7.0));
+
+        let matrix = Matrix::new(vec![vec![0, 9], vec![9, 0]]);
+        let image = heights_image(&matrix);
+        assert_eq!(
+            vec![heat(0.0), heat(1.0), heat(1.0), heat(0.0)],
+            image.pixels
+        );
+
+        let image = visibility_image(&[vec![true, false]]);
+        assert_eq!(vec![[60, 200, 80], [40, 40, 40]], image.pixels);
+
+        let image = scores_image(&[vec![0, 8, 0]]);
+        assert_eq!(vec![heat(0.0), heat(1.0), heat(0.0)], image.pixels);
+        assert_eq!(vec![heat(0.0)], scores_image(&[vec![0]]).pixels);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::Matrix;
+
+/// A grid position as (row, column).
+pub type Position = (usize, usize);
+
+/// Row and column steps of the four ways along the grid, in the order up,
+/// left, right, down.
+pub const CARDINAL: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// The cardinal directions followed by the four diagonals.
+pub const ALL_DIRECTIONS: [(isize, isize); 8] = [
+    (-1, 0),
+    (0, -1),
+    (0, 1),
+    (1, 0),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+impl Matrix {
+    fn step(&self, (row, col): Position, (d_row, d_col): (isize, isize)) -> Option<Position> {
+        let row = row.checked_add_signed(d_row)?;
+        let col = col.checked_add_signed(d_col)?;
+        (row < self.matrix.len() && col < self.n_columns()).then_some((row, col))
+    }
+
+    /// Trees seen from `from` by an observer `height` tall looking along
+    /// `direction`. As with the scenic score, the view stops at the first
+    /// tree at least as tall as the observer, which is itself seen.
+    pub fn line_of_sight(
+        &self,
+        from: Position,
+        height: u32,
+        direction: (isize, isize),
+    ) -> Vec<Position> {
+        let mut seen = vec![];
+        let mut current = from;
+        while let Some(next) = self.step(current, direction) {
+            seen.push(next);
+            if self.matrix[next.0][next.1] >= height {
+                break;
+            }
+            current = next;
+        }
+        seen
+    }
+
+    /// Every tree seen from `from` along any of `directions`.
+    pub fn visible_from(
+        &self,
+        from: Position,
+        height: u32,
+        directions: &[(isize, isize)],
+    ) -> Vec<Position> {
+        directions
+            .iter()
+            .flat_map(|&direction| self.line_of_sight(from, height, direction))
+            .collect()
+    }
+
+    /// The tree scoring highest under `score`, which is given each tree's
+    /// position and its viewing distance along each of `directions`, looking
+    /// from the height of the tree itself. Ties go to the first tree in
+    /// reading order.
+    pub fn best_cell(
+        &self,
+        directions: &[(isize, isize)],
+        score: impl Fn(Position, &[usize]) -> usize,
+    ) -> Option<(Position, usize)> {
+        let mut best: Option<(Position, usize)> = None;
+        for (row, heights) in self.matrix.iter().enumerate() {
+            for (col, &height) in heights.iter().enumerate() {
+                let distances: Vec<usize> = directions
+                    .iter()
+                    .map(|&direction| self.line_of_sight((row, col), height, direction).len())
+                    .collect();
+                let value = score((row, col), &distances);
+                if best.is_none_or(|(_, max)| value > max) {
+                    best = Some(((row, col), value));
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Matrix {
+        Matrix::parse(include_str!("../../sample_input.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let matrix = sample();
+        // looking up from the 5 in the middle of the second row
+        assert_eq!(vec![(0, 2)], matrix.line_of_sight((1, 2), 5, (-1, 0)));
+        assert_eq!(vec![(1, 1)], matrix.line_of_sight((1, 2), 5, (0, -1)));
+        assert_eq!(
+            vec![(1, 3), (1, 4)],
+            matrix.line_of_sight((1, 2), 5, (0, 1))
+        );
+        assert_eq!(
+            vec![(2, 2), (3, 2)],
+            matrix.line_of_sight((1, 2), 5, (1, 0))
+        );
+        // a taller observer sees over the 5 to the left
+        assert_eq!(
+            vec![(1, 1), (1, 0)],
+            matrix.line_of_sight((1, 2), 6, (0, -1))
+        );
+        assert_eq!(vec![(2, 1)], matrix.line_of_sight((1, 2), 5, (1, -1)));
+        assert_eq!(
+            vec![(1, 3), (0, 4)],
+            matrix.line_of_sight((2, 2), 4, (-1, 1))
+        );
+        assert!(matrix.line_of_sight((0, 0), 9, (-1, -1)).is_empty());
+
+        let seen = matrix.visible_from((2, 2), 4, &CARDINAL);
+        assert_eq!(vec![(1, 2), (2, 1), (2, 3), (2, 4), (3, 2)], seen);
+        assert_eq!(11, matrix.visible_from((2, 2), 4, &ALL_DIRECTIONS).len());
+    }
+
+    #[test]
+    fn test_matches_score_to() {
+        let matrix = sample();
+        for (row, heights) in matrix.matrix.iter().enumerate() {
+            for (col, &height) in heights.iter().enumerate() {
+                let distance =
+                    |direction| matrix.line_of_sight((row, col), height, direction).len();
+                let column = matrix.get_column(col);
+                assert_eq!(matrix.score_to_left(&column, row), distance((-1, 0)));
+                assert_eq!(matrix.score_to_left(heights, col), distance((0, -1)));
+                assert_eq!(matrix.score_to_right(heights, col), distance((0, 1)));
+                assert_eq!(matrix.score_to_right(&column, row), distance((1, 0)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_best_cell() {
+        let matrix = sample();
+        let product = |_: Position, distances: &[usize]| distances.iter().product();
+        assert_eq!(matrix.best_spot(), matrix.best_cell(&CARDINAL, product));
+
+        let sum = |_: Position, distances: &[usize]| distances.iter().sum();
+        assert_eq!(Some(((0, 3), 8)), matrix.best_cell(&CARDINAL, sum));
+        assert_eq!(Some(((0, 3), 12)), matrix.best_cell(&ALL_DIRECTIONS, sum));
+
+        // keep away from the edges of the forest
+        let inland = |(row, col): Position, distances: &[usize]| {
+            let product: usize = distances.iter().product();
+            if (1..=3).contains(&row) && (1..=3).contains(&col) {
+                product
+            } else {
+                0
+            }
+        };
+        assert_eq!(Some(((3, 2), 8)), matrix.best_cell(&CARDINAL, inland));
+
+        assert_eq!(None, Matrix::parse("").unwrap().best_cell(&CARDINAL, sum));
+    }
+}